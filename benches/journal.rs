@@ -0,0 +1,81 @@
+//! Times loading and recording with one and with ten years of daily
+//! standups, once with the plain data file and once with the journal. Run
+//! with `cargo bench --bench journal`.
+//!
+//! Each step runs the built binary, so the times are what a user waits for,
+//! start up and loading included. The journal only makes the write itself
+//! constant-time: every command, recording included, still loads the whole
+//! snapshot first, so both stores take longer as the history grows. The ten
+//! year rows against the one year rows show how much.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 10;
+
+/// One standup a day for the years up to 2015, a few entries each, written
+/// the way the data file keeps them.
+fn history(years: i32) -> String {
+    let mut days = vec![];
+    for year in 2016 - years..2016 {
+        for month in 1..13 {
+            for day in 1..29 {
+                days.push(format!(
+                    "{{\"blocker\":[\"Waiting on credentials for the staging cluster\"],\
+                     \"date\":\"{}-{:02}-{:02}\",\
+                     \"today\":[\"Finish the migration for the reporting tables\",\
+                     \"Sync with design about the onboarding flow\"],\
+                     \"yesterday\":[\"Reviewed pull requests for the billing service\",\
+                     \"Paired on the flaky integration tests\"]}}",
+                    year, month, day));
+            }
+        }
+    }
+    format!("[{}]", days.join(","))
+}
+
+fn standup(home: &Path, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_standup"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env_remove("STANDUP_PASSPHRASE")
+        .env_remove("STANDUP_KEYFILE")
+        .output()
+        .expect("could not run standup")
+        .status;
+    assert!(status.success(), "standup {} failed", args.join(" "));
+}
+
+fn time<F: FnMut()>(mut run: F) -> Duration {
+    let started = Instant::now();
+    for _ in 0..RUNS {
+        run();
+    }
+    started.elapsed() / RUNS
+}
+
+fn bench(name: &str, journal: bool, years: i32) {
+    let home: PathBuf = env::temp_dir().join(format!("standup-bench-{}-{}", name, years));
+    let _ = fs::remove_dir_all(&home);
+    fs::create_dir_all(&home).unwrap();
+    fs::write(home.join(".standup.json"), history(years)).unwrap();
+    if journal {
+        standup(&home, &["journal", "enable"]);
+    }
+
+    let load = time(|| standup(&home, &["show", "-d", "2015-12-28"]));
+    let record = time(|| standup(&home, &["today", "-d", "2015-12-28", "One more thing"]));
+    println!("{:<8} {:>2} years   load {:>10.2?}   record {:>10.2?}", name, years, load, record);
+    let _ = fs::remove_dir_all(&home);
+}
+
+fn main() {
+    for &years in &[1, 10] {
+        bench("file", false, years);
+        bench("journal", true, years);
+    }
+}
@@ -1,14 +1,23 @@
-use std::path::PathBuf;
-use std::env::home_dir;
-use std::fs::OpenOptions;
+use std::fs::{self, File};
+use std::io::Write;
 use chrono::*;
 use ::CliError;
 use ::StandupError;
-use standup::{Aspect, Standup, Manager};
+use standup::{Aspect, Standup, Manager, Conflict, Layout};
+use store::{Store, Backups, Retention};
+use config::{Profile, CarryOver};
+use jsonify::Style;
+use crypto::Secret;
+use calendar::Calendar;
 
 pub struct App {
-    date: Date<Local>,
+    date: NaiveDate,
     manager: Manager,
+    store: Store,
+    backups: Option<Backups>,
+    profile: Profile,
+    calendar: Calendar,
+    author: Option<String>,
 }
 
 ///
@@ -21,87 +30,544 @@ pub struct App {
 impl App {
     /// Creates a new App.
     ///
-    /// Takes an optional string for the date.
+    /// Takes the profile to work in and an optional string for the date, which
+    /// may be relative to today as the calendar reads it. Today is the
+    /// standup day on the profile's clock.
     ///
     /// Returns the new App if nothing errors when loading up the data.
-    pub fn new(date: Option<String>) -> Result<App, CliError> {
-        let manager = try!(App::load_manager());
-        let date = try!(App::get_date(date));
-        Ok(App { manager: manager, date: date })
-    }
-
-    fn get_path() -> Result<PathBuf, CliError> {
-        match home_dir() {
-            Some(ref mut path_buf) => {
-                path_buf.push(".standup.json");
-                // Recreate as immutable
-                Ok(path_buf.as_path().to_path_buf())
-            },
-            None => Err(CliError::Cli(StandupError::HomeDirNotFound))
+    pub fn new(profile: &Profile, date: Option<String>) -> Result<App, CliError> {
+        let store = try!(Store::locate(&profile.data_path));
+        let manager = try!(store.load());
+        let calendar = try!(profile.calendar());
+        let date = match date {
+            Some(date) => try!(calendar.resolve(&date, profile.clock.today()).ok_or(CliError::Cli(StandupError::InvalidDate))),
+            None => profile.clock.today(),
+        };
+        let backups = try!(Backups::open(Store::backups_dir(&profile.data_path)));
+        Ok(App {
+            manager: manager,
+            date: date,
+            store: store,
+            backups: backups,
+            profile: profile.clone(),
+            calendar: calendar,
+            author: profile.identity.clone(),
+        })
+    }
+
+    /// Works on someone's standup in a shared log rather than the configured
+    /// identity's. `None` goes back to the configured identity.
+    pub fn set_author(&mut self, author: Option<String>) {
+        self.author = author.or(self.profile.identity.clone());
+    }
+
+    pub fn date(&self) -> NaiveDate {
+        self.date
+    }
+
+    pub fn set_date(&mut self, date: NaiveDate) {
+        self.date = date;
+    }
+
+    /// The standup day it is now, which is not always the calendar date.
+    pub fn today(&self) -> NaiveDate {
+        self.profile.clock.today()
+    }
+
+    /// Reads the standups from the store again, picking up anything written
+    /// since the app was created.
+    pub fn reload(&mut self) -> Result<(), CliError> {
+        self.manager = try!(self.store.load());
+        Ok(())
+    }
+
+    /// Reads a date the way `App::new` does, relative dates included.
+    pub fn resolve_date(&self, date: &str) -> Result<NaiveDate, CliError> {
+        self.calendar.resolve(date, self.today()).ok_or(CliError::Cli(StandupError::InvalidDate))
+    }
+
+    /// Reads a date written as `YYYY-MM-DD`, and nothing else.
+    pub fn parse_date(date: &str) -> Result<NaiveDate, CliError> {
+        NaiveDate::parse_from_str(date, "%F").or(Err(CliError::Cli(StandupError::InvalidDate)))
+    }
+
+
+    fn flush_manager(&mut self, message: String) -> Result<(), CliError> {
+        try!(self.take_backup());
+        self.store.save(&mut self.manager, &message)
+    }
+
+    fn take_backup(&self) -> Result<Option<String>, CliError> {
+        match (self.backups.as_ref(), self.store.data_file()) {
+            (Some(backups), Some(data)) => backups.take(data),
+            _ => Ok(None),
         }
     }
 
-    fn get_date(date: Option<String>) -> Result<Date<Local>, CliError> {
-        match date {
-            Some(date_string) => {
-                NaiveDate::parse_from_str(&date_string, "%F")
-                    .or(Err(CliError::Cli(StandupError::InvalidDate)))
-                    .map(|date| Local.ymd(date.year(), date.month(), date.day()))
-            },
-            _ => Ok(Local::today())
+    fn author(&self) -> Option<&str> {
+        self.author.as_ref().map(|author| author.as_str())
+    }
+
+    /// The date, and who for when there is an author, for commit messages.
+    fn describe_day(&self) -> String {
+        match self.author {
+            Some(ref author) => format!("{} by {}", self.date.format("%F"), author),
+            None => self.date.format("%F").to_string(),
         }
     }
 
+    pub fn get_standup(&self) -> Standup {
+        self.manager.get_by(&self.date, self.author())
+            .unwrap_or(Standup::from_date(self.date.clone()).set_author(self.author.clone()))
+    }
 
-    fn load_manager() -> Result<Manager, CliError> {
-        App::get_path().and_then(|path| {
-            if path.is_file() {
-                OpenOptions::new()
-                    .read(true)
-                    .open(path)
-                    .map_err(CliError::Io)
-                    .and_then(Manager::from_reader)
-            } else {
-                OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .open(path)
-                    .map_err(CliError::Io)
-                    .map(|_| Manager::new())
+    /// Everyone's standups on the day, or an empty one when nobody has
+    /// posted yet.
+    pub fn day(&self) -> Vec<Standup> {
+        let standups: Vec<Standup> = self.manager.day(&self.date).into_iter().cloned().collect();
+        if standups.is_empty() { vec![self.get_standup()] } else { standups }
+    }
+
+    /// The team members who had not posted for the day by the deadline. Before
+    /// the deadline, and without one, nobody is missing. `now` is the time on
+    /// the profile's clock.
+    pub fn missing(&self, now: NaiveDateTime) -> Vec<String> {
+        let passed = match self.profile.deadline {
+            Some(deadline) => self.date < now.date() || (self.date == now.date() && now.time() >= deadline),
+            None => false,
+        };
+        if !passed {
+            return vec![];
+        }
+        let posted: Vec<Option<String>> = self.manager.day(&self.date).iter()
+            .map(|standup| standup.author.clone())
+            .collect();
+        self.profile.members.iter()
+            .filter(|member| !posted.contains(&Some(member.to_string())))
+            .cloned()
+            .collect()
+    }
+
+    pub fn deadline(&self) -> Option<NaiveTime> {
+        self.profile.deadline
+    }
+
+    /// The standup to add an entry to. A day that has not been started yet
+    /// picks up entries from the previous workday, as configured.
+    fn standup_to_edit(&self) -> Standup {
+        let standup = self.get_standup();
+        if self.manager.get_by(&self.date, self.author()).is_some() || self.profile.carry_over == CarryOver::Off {
+            return standup;
+        }
+        let mut standup = standup;
+        if let Some(previous) = self.previous_standup() {
+            for message in previous.today.iter() {
+                standup = standup.add(Aspect::Yesterday, message);
             }
+            if self.profile.carry_over == CarryOver::All {
+                for message in previous.blocker.iter() {
+                    standup = standup.add(Aspect::Blocker, message);
+                }
+            }
+        }
+        standup
+    }
+
+    /// The standup on the previous workday, by the same person.
+    pub fn previous_standup(&self) -> Option<Standup> {
+        self.previous_workday().and_then(|date| self.manager.get_by(&date, self.author()))
+    }
+
+    pub fn previous_workday(&self) -> Option<NaiveDate> {
+        self.calendar.previous_workday(self.date)
+    }
+
+    pub fn calendar(&self) -> &Calendar {
+        &self.calendar
+    }
+
+    /// Whether the author has written anything on the day.
+    pub fn has_entries(&self, date: NaiveDate) -> bool {
+        self.manager.get_by(&date, self.author()).map_or(false, |standup| {
+            Aspect::all().into_iter().any(|aspect| !standup.messages(aspect).is_empty())
         })
     }
 
-    fn flush_manager(&mut self) {
-        App::get_path().and_then(|path| {
-            let file = OpenOptions::new().create(true).write(true).truncate(true).open(path);
-            file.map_err(CliError::Io).and_then(|file| self.manager.flush(file))
-        }).unwrap();
+    /// How many workdays in a row, up to the app's date, have entries.
+    pub fn streak(&self) -> usize {
+        self.calendar.streak(self.date, |date| self.has_entries(date))
     }
 
-    pub fn get_standup(&self) -> Standup {
-        self.manager.get(&self.date).unwrap_or(Standup::from_date(self.date.clone()))
+    pub fn layout(&self) -> &Layout {
+        &self.profile.layout
+    }
+
+    pub fn manager(&self) -> &Manager {
+        &self.manager
     }
 
     pub fn standups(&self) -> Vec<&Standup> {
         self.manager.standups()
     }
 
-    pub fn record(&mut self, aspect: Aspect, message: String) {
-        let standup = self.get_standup().add(aspect, &message);
+    /// Returns the standups between the two dates, inclusive. A missing bound
+    /// leaves that side of the range open.
+    pub fn standups_between(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<&Standup> {
+        self.manager.standups().into_iter().filter(|standup| {
+            from.map_or(true, |from| standup.date >= from) && to.map_or(true, |to| standup.date <= to)
+        }).collect()
+    }
+
+    /// The standups between the two dates grouped by day, oldest first.
+    pub fn days_between(&self, from: Option<NaiveDate>, to: Option<NaiveDate>) -> Vec<Vec<&Standup>> {
+        let mut days: Vec<Vec<&Standup>> = vec![];
+        for standup in self.standups_between(from, to) {
+            if days.last().map_or(false, |day| day[0].date == standup.date) {
+                days.last_mut().unwrap().push(standup);
+            } else {
+                days.push(vec![standup]);
+            }
+        }
+        days
+    }
+
+    pub fn record(&mut self, aspect: Aspect, message: String) -> Result<(), CliError> {
+        let standup = self.standup_to_edit().add(aspect, &message);
         self.manager.insert(standup);
-        self.flush_manager();
+        let message = format!("Add {} entry for {}", aspect, self.describe_day());
+        self.flush_manager(message)
     }
 
-    pub fn delete(&mut self) -> Option<Standup> {
-        let standup = self.manager.delete(&self.date);
-        self.flush_manager();
-        standup
+    /// Puts the standup in place of the day's, as one change.
+    pub fn save(&mut self, standup: Standup) -> Result<(), CliError> {
+        self.manager.insert(standup);
+        let message = format!("Update standup for {}", self.describe_day());
+        self.flush_manager(message)
+    }
+
+    /// Adds several entries at once, writing them out in a single change.
+    pub fn record_all(&mut self, aspect: Aspect, messages: &[String]) -> Result<(), CliError> {
+        let entries: Vec<(Aspect, String)> = messages.iter().map(|message| (aspect, message.clone())).collect();
+        self.record_entries(&entries)
     }
 
-    pub fn delete_line(&mut self, aspect: Aspect, index: usize) {
-        let standup = self.get_standup().remove(aspect, index);
+    /// Adds entries to any of the aspects, in a single change.
+    pub fn record_entries(&mut self, entries: &[(Aspect, String)]) -> Result<(), CliError> {
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let mut standup = self.standup_to_edit();
+        for &(aspect, ref message) in entries {
+            standup = standup.add(aspect, message);
+        }
         self.manager.insert(standup);
-        self.flush_manager();
+        let message = format!("Add {} entries for {}", entries.len(), self.describe_day());
+        self.flush_manager(message)
+    }
+
+    pub fn delete(&mut self) -> Result<Option<Standup>, CliError> {
+        let standup = self.manager.delete_by(&self.date, self.author.as_ref().map(|a| a.as_str()));
+        if standup.is_some() {
+            let message = format!("Delete standup for {}", self.describe_day());
+            try!(self.flush_manager(message));
+        }
+        Ok(standup)
+    }
+
+    /// Merges the standups from another manager into this one and writes the
+    /// result back out. Returns any conflicts found along the way.
+    pub fn merge(&mut self, other: Manager) -> Result<Vec<Conflict>, CliError> {
+        let conflicts = self.manager.merge(other);
+        try!(self.flush_manager(format!("Merge {} conflict(s) from another file", conflicts.len())));
+        Ok(conflicts)
+    }
+
+    /// Rewrites the data file in the given JSON style, unless it is already
+    /// written that way.
+    pub fn set_style(&mut self, style: Style) -> Result<(), CliError> {
+        if self.manager.style() == style {
+            return Ok(());
+        }
+        self.manager.set_style(style);
+        self.flush_manager(format!("Reformat standups as {} JSON", style.name()))
+    }
+
+    /// Rewrites the data file encrypted with a key derived from the secret.
+    pub fn encrypt(&mut self, secret: Secret) -> Result<(), CliError> {
+        self.store = try!(self.store.encrypt(&self.manager, secret));
+        Ok(())
+    }
+
+    /// Rewrites the data file as plain JSON.
+    pub fn decrypt(&mut self) -> Result<(), CliError> {
+        self.store = try!(self.store.decrypt(&self.manager));
+        Ok(())
+    }
+
+    /// Starts appending changes to a journal instead of rewriting the file.
+    pub fn enable_journal(&mut self) -> Result<(), CliError> {
+        self.store = try!(self.store.enable_journal());
+        Ok(())
+    }
+
+    /// Folds the journal back into the file and stops journaling.
+    pub fn disable_journal(&mut self) -> Result<(), CliError> {
+        self.store = try!(self.store.disable_journal(&self.manager));
+        Ok(())
+    }
+
+    pub fn compact(&mut self) -> Result<(), CliError> {
+        self.store.compact(&self.manager)
+    }
+
+    pub fn backups(&self) -> Option<&Backups> {
+        self.backups.as_ref()
+    }
+
+    /// Starts keeping a backup of the data file before every write.
+    pub fn enable_backups(&mut self, retention: Retention) -> Result<(), CliError> {
+        if self.store.data_file().is_none() {
+            return Err(CliError::Cli(StandupError::BackupsNotSupported));
+        }
+        let dir = Store::backups_dir(&self.profile.data_path);
+        self.backups = Some(try!(Backups::enable(dir, retention)));
+        Ok(())
+    }
+
+    /// Loads the standups held in a backup.
+    pub fn read_backup(&self, id: &str) -> Result<Manager, CliError> {
+        let backups = try!(self.backups.as_ref().ok_or(CliError::Cli(StandupError::BackupsNotEnabled)));
+        let bytes = try!(backups.read(id));
+        self.store.decode(&bytes)
+    }
+
+    /// Puts a backup back in place of the data file. The current file is
+    /// backed up first, so a restore can itself be undone.
+    pub fn restore_backup(&mut self, id: &str) -> Result<(), CliError> {
+        let data = try!(self.store.data_file()
+                        .map(|path| path.to_path_buf())
+                        .ok_or(CliError::Cli(StandupError::BackupsNotSupported)));
+        let backups = try!(self.backups.as_ref().ok_or(CliError::Cli(StandupError::BackupsNotEnabled)));
+        let bytes = try!(backups.read(id));
+        self.manager = try!(self.store.decode(&bytes));
+        try!(self.take_backup());
+        // Written alongside and renamed over, so a crash part way through
+        // leaves the current file as it was.
+        let temp = data.with_extension("json.tmp");
+        try!(File::create(&temp)
+             .and_then(|mut file| file.write_all(&bytes))
+             .map_err(CliError::Io));
+        fs::rename(&temp, &data).map_err(CliError::Io)
+    }
+
+    /// Moves the standups into a git repository so they can be synced,
+    /// optionally cloning an existing remote first.
+    pub fn enable_sync(&mut self, url: Option<String>) -> Result<(), CliError> {
+        let url = url.as_ref().map(|r| r.as_str());
+        self.store = try!(Store::enable_sync(&self.profile.data_path, &self.manager, url, &self.profile.sync_remote));
+        self.manager = try!(self.store.load());
+        if url.is_some() {
+            try!(self.store.sync(&self.profile.sync_remote));
+        }
+        Ok(())
+    }
+
+    /// Pulls in changes from the remote and pushes local ones.
+    pub fn sync(&mut self) -> Result<(), CliError> {
+        try!(self.store.sync(&self.profile.sync_remote));
+        self.manager = try!(self.store.load());
+        Ok(())
+    }
+
+    /// Replaces the text of an entry, failing if there is no entry at the
+    /// index.
+    pub fn edit_line(&mut self, aspect: Aspect, index: usize, message: String) -> Result<(), CliError> {
+        let standup = self.get_standup();
+        if index >= standup.messages(aspect).len() {
+            return Err(CliError::Cli(StandupError::LineNotFound));
+        }
+        self.manager.insert(standup.replace(aspect, index, &message));
+        let message = format!("Edit {} entry {} for {}", aspect, index + 1, self.describe_day());
+        self.flush_manager(message)
+    }
+
+    /// Moves an entry to `to_index` in the target aspect, or to the end of it
+    /// when no index is given.
+    pub fn move_line(&mut self, aspect: Aspect, index: usize, target: Aspect, to_index: Option<usize>)
+                     -> Result<(), CliError> {
+        let standup = self.get_standup();
+        let last = standup.messages(aspect).len().saturating_sub(1);
+        let moved = match (target == aspect, to_index) {
+            (true, Some(to)) => standup.move_within(aspect, index, to),
+            (true, None) => standup.move_within(aspect, index, last),
+            (false, Some(to)) => standup.take(aspect, index).and_then(|(standup, message)| {
+                standup.insert(target, to, &message)
+            }),
+            (false, None) => standup.move_to(aspect, index, target),
+        };
+        self.manager.insert(try!(moved.map_err(CliError::Cli)));
+        let message = format!("Move {} entry {} to {} for {}", aspect, index + 1, target, self.describe_day());
+        self.flush_manager(message)
+    }
+
+    /// Moves an entry to the end of the target aspect on another day, in one
+    /// change.
+    pub fn move_to_date(&mut self, aspect: Aspect, index: usize, date: NaiveDate, target: Aspect)
+                        -> Result<(), CliError> {
+        if date == self.date {
+            return self.move_line(aspect, index, target, None);
+        }
+        let (standup, message) = try!(self.get_standup().take(aspect, index).map_err(CliError::Cli));
+        let other = self.manager.get_by(&date, self.author())
+            .unwrap_or(Standup::from_date(date).set_author(self.author.clone()))
+            .add(target, &message);
+        self.manager.insert(standup);
+        self.manager.insert(other);
+        let message = format!("Move {} entry {} for {} to {}", aspect, index + 1, self.describe_day(), date.format("%F"));
+        self.flush_manager(message)
+    }
+
+    pub fn delete_line(&mut self, aspect: Aspect, index: usize) -> Result<(), CliError> {
+        let (standup, _) = try!(self.get_standup().take(aspect, index).map_err(CliError::Cli));
+        self.manager.insert(standup);
+        let message = format!("Delete {} entry {} for {}", aspect, index + 1, self.describe_day());
+        self.flush_manager(message)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use config::Config;
+    use store::test::scratch;
+    use standup::Aspect;
+    use toml::Value;
+
+    /// An app on the date with its data in a scratch directory.
+    pub fn app(name: &str, date: &str) -> App {
+        let data = scratch(name).join("standup.json");
+        let toml = format!("carry_over = \"all\"\n[data]\npath = {:?}\n", data.to_str().unwrap());
+        let root = toml.parse::<Value>().unwrap();
+        let config = Config::from_table(data.with_extension("toml"), root.as_table().unwrap()).unwrap();
+        App::new(config.profile(None).unwrap(), Some(date.to_string())).unwrap()
+    }
+
+    #[test]
+    fn it_will_carry_over_from_the_previous_workday() {
+        let mut app = app("app-carry-over", "2016-03-04");
+        app.record(Aspect::Today, "ship it".to_string()).unwrap();
+        app.record(Aspect::Blocker, "waiting on review".to_string()).unwrap();
+
+        // The 7th is the Monday after.
+        app.date = NaiveDate::from_ymd_opt(2016, 3, 7).unwrap();
+        app.record(Aspect::Today, "celebrate".to_string()).unwrap();
+        let standup = app.get_standup();
+        assert_eq!(standup.yesterday, vec!["ship it"]);
+        assert_eq!(standup.blocker, vec!["waiting on review"]);
+        assert_eq!(standup.today, vec!["celebrate"]);
+    }
+
+    #[test]
+    fn it_will_keep_each_persons_entries_apart() {
+        let mut app = app("app-team", "2016-03-04");
+        app.set_author(Some("alice".to_string()));
+        app.record(Aspect::Today, "review".to_string()).unwrap();
+        app.set_author(Some("bob".to_string()));
+        app.record(Aspect::Today, "deploy".to_string()).unwrap();
+        assert_eq!(app.get_standup().today, vec!["deploy"]);
+        assert_eq!(app.day().len(), 2);
+        app.delete().unwrap();
+        assert_eq!(app.day().len(), 1);
+    }
+
+    #[test]
+    fn it_will_flag_members_who_have_not_posted_by_the_deadline() {
+        let mut app = app("app-missing", "2016-03-04");
+        app.profile.members = vec!["alice".to_string(), "bob".to_string()];
+        app.set_author(Some("alice".to_string()));
+        app.record(Aspect::Today, "review".to_string()).unwrap();
+
+        assert_eq!(app.missing(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap().and_hms_opt(10, 0, 0).unwrap()).len(), 0);
+        app.profile.deadline = Some(NaiveTime::from_hms_opt(9, 30, 0).unwrap());
+        assert_eq!(app.missing(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap().and_hms_opt(9, 0, 0).unwrap()).len(), 0);
+        assert_eq!(app.missing(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap().and_hms_opt(9, 30, 0).unwrap()), vec!["bob"]);
+        assert_eq!(app.missing(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap().and_hms_opt(8, 0, 0).unwrap()), vec!["bob"]);
+    }
+
+    #[test]
+    fn it_can_record_several_entries_at_once() {
+        let mut app = app("app-record-all", "2016-03-04");
+        app.record_all(Aspect::Yesterday, &["one".to_string(), "two".to_string()]).unwrap();
+        app.reload().unwrap();
+        assert_eq!(app.get_standup().yesterday, vec!["one", "two"]);
+        app.record_entries(&[(Aspect::Blocker, "ops".to_string()), (Aspect::Yesterday, "three".to_string())]).unwrap();
+        app.reload().unwrap();
+        assert_eq!(app.get_standup().yesterday, vec!["one", "two", "three"]);
+        assert_eq!(app.get_standup().blocker, vec!["ops"]);
+    }
+
+    #[test]
+    fn it_can_move_an_entry_to_another_day() {
+        let mut app = app("app-move-date", "2016-03-04");
+        app.record(Aspect::Today, "ship it".to_string()).unwrap();
+        app.move_to_date(Aspect::Today, 0, NaiveDate::from_ymd_opt(2016, 3, 7).unwrap(), Aspect::Blocker).unwrap();
+        assert_eq!(app.get_standup().today.len(), 0);
+        app.set_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap());
+        assert_eq!(app.get_standup().blocker, vec!["ship it"]);
+        assert!(app.move_to_date(Aspect::Today, 0, NaiveDate::from_ymd_opt(2016, 3, 4).unwrap(), Aspect::Today).is_err());
+    }
+
+    #[test]
+    fn it_will_carry_over_from_before_time_off() {
+        let mut app = app("app-carry-pto", "2016-03-11");
+        app.record(Aspect::Today, "hand over".to_string()).unwrap();
+        app.calendar.pto.push((NaiveDate::from_ymd_opt(2016, 3, 14).unwrap(), NaiveDate::from_ymd_opt(2016, 3, 18).unwrap()));
+        app.set_date(NaiveDate::from_ymd_opt(2016, 3, 21).unwrap());
+        app.record(Aspect::Today, "catch up".to_string()).unwrap();
+        assert_eq!(app.get_standup().yesterday, vec!["hand over"]);
+        assert_eq!(app.streak(), 2);
+    }
+
+    #[test]
+    fn it_will_not_carry_over_when_turned_off() {
+        let mut app = app("app-no-carry-over", "2016-03-04");
+        app.profile.carry_over = CarryOver::Off;
+        app.record(Aspect::Today, "ship it".to_string()).unwrap();
+        app.date = NaiveDate::from_ymd_opt(2016, 3, 7).unwrap();
+        app.record(Aspect::Today, "celebrate".to_string()).unwrap();
+        assert_eq!(app.get_standup().yesterday.len(), 0);
+    }
+
+    #[test]
+    fn it_will_not_write_when_there_is_nothing_to_delete() {
+        let mut app = app("app-delete-nothing", "2016-03-04");
+        let path = app.profile.data_path.clone();
+        let modified = || fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+        let before = modified();
+        assert_eq!(app.delete().unwrap(), None);
+        assert_eq!(modified(), before);
+    }
+
+    #[test]
+    fn it_will_not_rewrite_a_file_already_in_the_style() {
+        let mut app = app("app-same-style", "2016-03-04");
+        app.record(Aspect::Today, "review".to_string()).unwrap();
+        let path = app.profile.data_path.clone();
+        let modified = || fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+        let before = modified();
+        app.set_style(Style::Compact).unwrap();
+        assert_eq!(modified(), before);
+        app.set_style(Style::Pretty).unwrap();
+        assert_eq!(Style::detect(&fs::read_to_string(&path).unwrap()), Style::Pretty);
+    }
+
+    #[test]
+    fn it_will_not_enable_backups_it_cannot_take() {
+        let mut app = app("app-backup-journal", "2016-03-04");
+        app.enable_journal().unwrap();
+        match app.enable_backups(Retention::default()) {
+            Err(CliError::Cli(StandupError::BackupsNotSupported)) => {},
+            _ => panic!("expected backups to be refused"),
+        }
     }
 }
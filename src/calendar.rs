@@ -0,0 +1,323 @@
+use chrono::*;
+
+/// A day off for everyone, from the config or an `.ics` file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Holiday {
+    pub date: NaiveDate,
+    pub name: String,
+}
+
+/// What kind of day a date is. Holidays and PTO win over the workweek, so a
+/// holiday on a weekend still shows up as one.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Day {
+    Workday,
+    Off,
+    Holiday(String),
+    Pto,
+}
+
+impl Day {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Day::Workday => "workday",
+            Day::Off => "off",
+            Day::Holiday(_) => "holiday",
+            Day::Pto => "pto",
+        }
+    }
+}
+
+///
+/// ## Calendar
+///
+/// Which days are workdays: the days of the workweek, less holidays and time
+/// off. "Yesterday" always means the previous workday, so on a Monday it is
+/// the Friday before, and after a vacation it is the last day worked.
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Calendar {
+    pub workweek: Vec<Weekday>,
+    pub holidays: Vec<Holiday>,
+    /// Ranges of time off, both ends included.
+    pub pto: Vec<(NaiveDate, NaiveDate)>,
+}
+
+/// How far to look for a workday before giving up, so a calendar with no
+/// workdays at all cannot loop forever.
+const SEARCH_DAYS: usize = 366;
+
+/// The furthest a relative date such as `-2` can reach, in workdays, which is
+/// decades either way and keeps the search short.
+const MAX_OFFSET: i64 = 10_000;
+
+impl Calendar {
+    pub fn day(&self, date: NaiveDate) -> Day {
+        if let Some(holiday) = self.holidays.iter().find(|holiday| holiday.date == date) {
+            return Day::Holiday(holiday.name.clone());
+        }
+        if self.pto.iter().any(|&(from, to)| from <= date && date <= to) {
+            return Day::Pto;
+        }
+        if self.workweek.contains(&date.weekday()) { Day::Workday } else { Day::Off }
+    }
+
+    pub fn is_workday(&self, date: NaiveDate) -> bool {
+        self.day(date) == Day::Workday
+    }
+
+    pub fn previous_workday(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.step(date, -1)
+    }
+
+    pub fn next_workday(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.step(date, 1)
+    }
+
+    /// The workday `by` workdays away, backwards when negative. `None` when
+    /// it is further than `MAX_OFFSET` or past the dates chrono can hold.
+    fn step(&self, date: NaiveDate, by: i64) -> Option<NaiveDate> {
+        if by < -MAX_OFFSET || by > MAX_OFFSET {
+            return None;
+        }
+        let mut date = date;
+        for _ in 0..by.abs() {
+            let mut found = None;
+            for _ in 0..SEARCH_DAYS {
+                date = match if by < 0 { date.pred_opt() } else { date.succ_opt() } {
+                    Some(date) => date,
+                    None => return None,
+                };
+                if self.is_workday(date) {
+                    found = Some(date);
+                    break;
+                }
+            }
+            date = match found {
+                Some(date) => date,
+                None => return None,
+            };
+        }
+        Some(date)
+    }
+
+    /// Reads a date as `YYYY-MM-DD`, or relative to `today` as `today`,
+    /// `yesterday` and `tomorrow`, meaning the previous and next workday, or a
+    /// number of workdays away such as `-2` or `+1`.
+    pub fn resolve(&self, text: &str, today: NaiveDate) -> Option<NaiveDate> {
+        match text {
+            "today" => return Some(today),
+            "yesterday" => return self.previous_workday(today),
+            "tomorrow" => return self.next_workday(today),
+            _ => {},
+        }
+        if text.starts_with("-") || text.starts_with("+") {
+            return text[1..].parse::<i64>().ok()
+                .and_then(|days| if text.starts_with("-") { days.checked_neg() } else { Some(days) })
+                .and_then(|days| self.step(today, days));
+        }
+        NaiveDate::parse_from_str(text, "%F").ok()
+    }
+
+    /// How many workdays in a row, back from `today`, have entries. Today
+    /// only counts once it has some, so the streak is not broken before the
+    /// standup has been written.
+    pub fn streak<F>(&self, today: NaiveDate, has_entries: F) -> usize where F: Fn(NaiveDate) -> bool {
+        let mut streak = 0;
+        let mut date = if self.is_workday(today) && has_entries(today) {
+            Some(today)
+        } else {
+            self.previous_workday(today)
+        };
+        while let Some(day) = date {
+            if !has_entries(day) {
+                break;
+            }
+            streak += 1;
+            date = self.previous_workday(day);
+        }
+        streak
+    }
+
+    /// Every day of the month the date falls in.
+    pub fn month(&self, date: NaiveDate) -> Vec<NaiveDate> {
+        let mut days = vec![];
+        let mut day = NaiveDate::from_ymd_opt(date.year(), date.month(), 1);
+        while let Some(current) = day.filter(|day| day.month() == date.month()) {
+            days.push(current);
+            day = current.succ_opt();
+        }
+        days
+    }
+
+    /// One line per day of the month the date falls in, saying what kind of
+    /// day it is and marking days with entries with `*`.
+    pub fn render_month<F>(&self, date: NaiveDate, has_entries: F) -> String where F: Fn(NaiveDate) -> bool {
+        let mut out = format!("{}\n", date.format("%B %Y"));
+        for day in self.month(date) {
+            let kind = self.day(day);
+            let note = match kind {
+                Day::Holiday(ref name) => name.clone(),
+                _ => String::new(),
+            };
+            let marker = if has_entries(day) { "*" } else { " " };
+            let line = format!("{} {} {:<8}{}", day.format("%F %a"), marker, kind.name(), note);
+            out.push_str(line.trim_end());
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Reads a holiday written as `2016-12-26` or `2016-12-26 Boxing Day`.
+pub fn parse_holiday(text: &str) -> Option<Holiday> {
+    let mut parts = text.trim().splitn(2, ' ');
+    let date = parts.next().and_then(|date| NaiveDate::parse_from_str(date, "%F").ok());
+    date.map(|date| Holiday { date: date, name: parts.next().unwrap_or("").trim().to_string() })
+}
+
+/// Reads time off written as `2016-03-07..2016-03-18`, or a single day.
+pub fn parse_range(text: &str) -> Option<(NaiveDate, NaiveDate)> {
+    let mut parts = text.trim().splitn(2, "..");
+    let from = parts.next().and_then(|date| NaiveDate::parse_from_str(date.trim(), "%F").ok());
+    let to = match parts.next() {
+        Some(to) => NaiveDate::parse_from_str(to.trim(), "%F").ok(),
+        None => from,
+    };
+    match (from, to) {
+        (Some(from), Some(to)) if from <= to => Some((from, to)),
+        _ => None,
+    }
+}
+
+/// Reads the all-day events of an iCalendar file as holidays. An event that
+/// spans several days becomes a holiday on each of them; the end date is not
+/// included, as iCalendar has it.
+pub fn parse_ics(text: &str) -> Vec<Holiday> {
+    // Long lines are folded onto the next line, which starts with a space.
+    let unfolded = text.replace("\r\n", "\n").replace("\n ", "").replace("\n\t", "");
+    let mut holidays = vec![];
+    let (mut start, mut end, mut name) = (None, None, String::new());
+    for line in unfolded.lines() {
+        let mut parts = line.splitn(2, ':');
+        let (key, value) = match (parts.next(), parts.next()) {
+            (Some(key), Some(value)) => (key.split(';').next().unwrap_or(""), value.trim()),
+            _ => continue,
+        };
+        match key {
+            "BEGIN" if value == "VEVENT" => {
+                start = None;
+                end = None;
+                name.clear();
+            },
+            "DTSTART" => start = ics_date(value),
+            "DTEND" => end = ics_date(value),
+            "SUMMARY" => name = value.replace("\\,", ",").replace("\\;", ";"),
+            "END" if value == "VEVENT" => {
+                if let Some(start) = start {
+                    let end = end.filter(|end| *end > start).or(start.succ_opt()).unwrap_or(start);
+                    let mut date = Some(start);
+                    while let Some(day) = date.filter(|day| *day < end) {
+                        holidays.push(Holiday { date: day, name: name.clone() });
+                        date = day.succ_opt();
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+    holidays
+}
+
+fn ics_date(value: &str) -> Option<NaiveDate> {
+    value.get(..8).and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn calendar() -> Calendar {
+        Calendar {
+            workweek: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            holidays: vec![parse_holiday("2016-03-25 Good Friday").unwrap()],
+            pto: vec![parse_range("2016-03-14..2016-03-18").unwrap()],
+        }
+    }
+
+    #[test]
+    fn it_will_skip_weekends_holidays_and_pto() {
+        let calendar = calendar();
+        assert_eq!(calendar.previous_workday(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap()), Some(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap()));
+        assert_eq!(calendar.previous_workday(NaiveDate::from_ymd_opt(2016, 3, 21).unwrap()), Some(NaiveDate::from_ymd_opt(2016, 3, 11).unwrap()));
+        assert_eq!(calendar.next_workday(NaiveDate::from_ymd_opt(2016, 3, 24).unwrap()), Some(NaiveDate::from_ymd_opt(2016, 3, 28).unwrap()));
+        assert_eq!(calendar.day(NaiveDate::from_ymd_opt(2016, 3, 25).unwrap()), Day::Holiday("Good Friday".to_string()));
+        let never = Calendar { workweek: vec![], holidays: vec![], pto: vec![] };
+        assert_eq!(never.previous_workday(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap()), None);
+    }
+
+    #[test]
+    fn it_can_resolve_relative_dates() {
+        let calendar = calendar();
+        let monday = NaiveDate::from_ymd_opt(2016, 3, 21).unwrap();
+        assert_eq!(calendar.resolve("today", monday), Some(monday));
+        assert_eq!(calendar.resolve("yesterday", monday), Some(NaiveDate::from_ymd_opt(2016, 3, 11).unwrap()));
+        assert_eq!(calendar.resolve("-2", monday), Some(NaiveDate::from_ymd_opt(2016, 3, 10).unwrap()));
+        assert_eq!(calendar.resolve("+4", monday), Some(NaiveDate::from_ymd_opt(2016, 3, 28).unwrap()));
+        assert_eq!(calendar.resolve("2016-02-29", monday), Some(NaiveDate::from_ymd_opt(2016, 2, 29).unwrap()));
+        assert_eq!(calendar.resolve("someday", monday), None);
+    }
+
+    #[test]
+    fn it_will_not_resolve_dates_out_of_range() {
+        let calendar = calendar();
+        let monday = NaiveDate::from_ymd_opt(2016, 3, 21).unwrap();
+        assert_eq!(calendar.resolve("-99999999", monday), None);
+        assert_eq!(calendar.resolve("+-9223372036854775808", monday), None);
+        assert_eq!(calendar.resolve("--9223372036854775808", monday), None);
+        assert_eq!(calendar.resolve("-1", NaiveDate::MIN), None);
+        assert_eq!(calendar.resolve("+1", NaiveDate::MAX), None);
+    }
+
+    #[test]
+    fn it_will_count_the_streak_in_workdays() {
+        let calendar = calendar();
+        let posted = [NaiveDate::from_ymd_opt(2016, 3, 10).unwrap(), NaiveDate::from_ymd_opt(2016, 3, 11).unwrap(), NaiveDate::from_ymd_opt(2016, 3, 21).unwrap()];
+        assert_eq!(calendar.streak(NaiveDate::from_ymd_opt(2016, 3, 21).unwrap(), |date| posted.contains(&date)), 3);
+        assert_eq!(calendar.streak(NaiveDate::from_ymd_opt(2016, 3, 22).unwrap(), |date| posted.contains(&date)), 3);
+        assert_eq!(calendar.streak(NaiveDate::from_ymd_opt(2016, 3, 23).unwrap(), |date| posted.contains(&date)), 0);
+    }
+
+    #[test]
+    fn it_can_read_holidays_from_ics() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20161226\r\nDTEND;VALUE=DATE:20161228\r\n\
+                   SUMMARY:Christmas\\, observed\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20170101T000000\r\n\
+                   SUMMARY:New Year\r\n  's Day\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let holidays = parse_ics(ics);
+        let dates: Vec<String> = holidays.iter().map(|holiday| holiday.date.format("%F").to_string()).collect();
+        assert_eq!(dates, vec!["2016-12-26", "2016-12-27", "2017-01-01"]);
+        assert_eq!(holidays[0].name, "Christmas, observed");
+        assert_eq!(holidays[2].name, "New Year 's Day");
+    }
+
+    #[test]
+    fn it_will_skip_ics_dates_that_are_not_dates() {
+        let ics = "BEGIN:VEVENT\nDTSTART:2016ü1226\nSUMMARY:Broken\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nDTSTART:2016122ü\nEND:VEVENT\n\
+                   BEGIN:VEVENT\nDTSTART:20161226\nSUMMARY:Bœxing Day\nEND:VEVENT\n";
+        let holidays = parse_ics(ics);
+        assert_eq!(holidays, vec![Holiday { date: NaiveDate::from_ymd_opt(2016, 12, 26).unwrap(), name: "Bœxing Day".to_string() }]);
+    }
+
+    #[test]
+    fn it_can_render_a_month() {
+        let month = calendar().render_month(NaiveDate::from_ymd_opt(2016, 3, 1).unwrap(), |date| date == NaiveDate::from_ymd_opt(2016, 3, 24).unwrap());
+        let lines: Vec<&str> = month.lines().collect();
+        assert_eq!(lines.len(), 32);
+        assert_eq!(lines[0], "March 2016");
+        assert_eq!(lines[5], "2016-03-05 Sat   off");
+        assert_eq!(lines[14], "2016-03-14 Mon   pto");
+        assert_eq!(lines[24], "2016-03-24 Thu * workday");
+        assert_eq!(lines[25], "2016-03-25 Fri   holiday Good Friday");
+    }
+}
@@ -0,0 +1,166 @@
+use serde_json::Value;
+use serde_json::builder::{ObjectBuilder, ArrayBuilder};
+use standup::{Standup, Aspect, Layout};
+
+pub static CHATS: &'static [&'static str] = &["slack", "teams", "mattermost"];
+
+/// The chat a webhook belongs to, which decides the shape of the payload.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Chat {
+    /// Block Kit, with a header and a section per aspect.
+    Slack,
+    /// A message card, shown with a red edge when there are blockers.
+    Teams,
+    /// A single markdown message.
+    Mattermost,
+}
+
+impl Chat {
+    pub fn from_name(name: &str) -> Option<Chat> {
+        match name {
+            "slack" => Some(Chat::Slack),
+            "teams" => Some(Chat::Teams),
+            "mattermost" => Some(Chat::Mattermost),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Chat::Slack => "slack",
+            Chat::Teams => "teams",
+            Chat::Mattermost => "mattermost",
+        }
+    }
+}
+
+/// Renders the standup as the JSON body of an incoming webhook.
+pub fn serialize(standup: &Standup, layout: &Layout, chat: Chat) -> String {
+    let value = match chat {
+        Chat::Slack => slack(standup, layout),
+        Chat::Teams => teams(standup, layout),
+        Chat::Mattermost => mattermost(standup, layout),
+    };
+    format!("{}", value)
+}
+
+fn title(standup: &Standup, layout: &Layout) -> String {
+    let date = standup.date.format(&layout.date_format);
+    match standup.author {
+        Some(ref author) => format!("Standup {} ({})", date, author),
+        None => format!("Standup {}", date),
+    }
+}
+
+/// The aspects to show, in order, with their entries.
+fn sections<'a>(standup: &'a Standup, layout: &Layout) -> Vec<(Aspect, &'a Vec<String>)> {
+    layout.order.iter()
+        .map(|&aspect| (aspect, standup.messages(aspect)))
+        .filter(|&(aspect, messages)| !(messages.is_empty() && layout.hide_empty.contains(&aspect)))
+        .collect()
+}
+
+fn heading(aspect: Aspect, emphasis: &str) -> String {
+    let warning = if aspect == Aspect::Blocker { ":warning: " } else { "" };
+    format!("{}{}{}{}", warning, emphasis, aspect, emphasis)
+}
+
+fn slack(standup: &Standup, layout: &Layout) -> Value {
+    let title = title(standup, layout);
+    let mut blocks = ArrayBuilder::new().push_object(|block| {
+        block.insert("type", "header")
+            .insert_object("text", |text| text.insert("type", "plain_text").insert("text", title.clone()))
+    });
+    for (aspect, messages) in sections(standup, layout) {
+        let mut text = heading(aspect, "*");
+        for message in messages.iter() {
+            text.push_str(&format!("\n• {}", slack_escape(message)));
+        }
+        blocks = blocks.push_object(|block| {
+            block.insert("type", "section")
+                .insert_object("text", |object| object.insert("type", "mrkdwn").insert("text", text))
+        });
+    }
+    ObjectBuilder::new()
+        .insert("text", title.clone())
+        .insert("blocks", blocks.unwrap())
+        .unwrap()
+}
+
+/// Slack reads `&`, `<` and `>` as markup, so they have to be escaped.
+fn slack_escape(text: &str) -> String {
+    text.replace("&", "&amp;").replace("<", "&lt;").replace(">", "&gt;")
+}
+
+fn teams(standup: &Standup, layout: &Layout) -> Value {
+    let title = title(standup, layout);
+    let mut cards = ArrayBuilder::new();
+    for (aspect, messages) in sections(standup, layout) {
+        let text = messages.iter().map(|message| format!("- {}", message)).collect::<Vec<String>>().join("\n");
+        cards = cards.push_object(|section| {
+            section.insert("activityTitle", heading(aspect, "**")).insert("text", text)
+        });
+    }
+    ObjectBuilder::new()
+        .insert("@type", "MessageCard")
+        .insert("@context", "https://schema.org/extensions")
+        .insert("themeColor", if standup.is_blocked() { "D73A49" } else { "2EB886" })
+        .insert("summary", title.clone())
+        .insert("title", title)
+        .insert("sections", cards.unwrap())
+        .unwrap()
+}
+
+fn mattermost(standup: &Standup, layout: &Layout) -> Value {
+    let mut text = format!("#### {}", title(standup, layout));
+    for (aspect, messages) in sections(standup, layout) {
+        text.push_str(&format!("\n{}", heading(aspect, "**")));
+        for message in messages.iter() {
+            text.push_str(&format!("\n- {}", message));
+        }
+    }
+    ObjectBuilder::new().insert("text", text).unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::{from_str, Value};
+    use standup::{Standup, Aspect, Layout};
+    use chrono::*;
+
+    fn standup() -> Standup {
+        Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())
+            .add(Aspect::Today, "review <PR>")
+            .add(Aspect::Blocker, "waiting")
+    }
+
+    fn payload(chat: Chat) -> Value {
+        from_str(&serialize(&standup(), &Layout::default(), chat)).unwrap()
+    }
+
+    #[test]
+    fn it_will_render_slack_blocks() {
+        let payload = payload(Chat::Slack);
+        let blocks = payload.find("blocks").unwrap().as_array().unwrap();
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(blocks[0].lookup("text.text").unwrap().as_string(), Some("Standup 2016-03-07 - Monday"));
+        assert_eq!(blocks[1].lookup("text.text").unwrap().as_string(), Some("*today*\n• review &lt;PR&gt;"));
+        assert_eq!(blocks[3].lookup("text.text").unwrap().as_string(), Some(":warning: *blocker*\n• waiting"));
+    }
+
+    #[test]
+    fn it_will_render_a_teams_card() {
+        let payload = payload(Chat::Teams);
+        assert_eq!(payload.find("@type").unwrap().as_string(), Some("MessageCard"));
+        assert_eq!(payload.find("themeColor").unwrap().as_string(), Some("D73A49"));
+        assert_eq!(payload.find("sections").unwrap().as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn it_will_render_mattermost_markdown() {
+        assert_eq!(payload(Chat::Mattermost).find("text").unwrap().as_string(), Some(concat!(
+            "#### Standup 2016-03-07 - Monday\n",
+            "**today**\n- review <PR>\n**yesterday**\n:warning: **blocker**\n- waiting")));
+    }
+}
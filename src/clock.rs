@@ -0,0 +1,133 @@
+use chrono::*;
+use chrono_tz::Tz;
+
+///
+/// ## Clock
+///
+/// Decides which standup day it is. Days are kept in the configured time
+/// zone, or the system's when there is none, and start at the rollover time
+/// rather than at midnight, so that work written up late at night still
+/// lands on the day it was done.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Clock {
+    pub zone: Option<Tz>,
+    pub rollover: NaiveTime,
+}
+
+impl Clock {
+    /// The system's zone, with days starting at midnight.
+    pub fn system() -> Clock {
+        Clock { zone: None, rollover: NaiveTime::from_hms_opt(0, 0, 0).unwrap() }
+    }
+
+    /// The time on the wall clock in the zone at the instant.
+    pub fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self.zone {
+            Some(zone) => at.with_timezone(&zone).naive_local(),
+            None => at.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    /// The standup day the instant falls on.
+    pub fn day(&self, at: DateTime<Utc>) -> NaiveDate {
+        let local = self.local(at);
+        if local.time() < self.rollover {
+            local.date().pred_opt().unwrap_or(local.date())
+        } else {
+            local.date()
+        }
+    }
+
+    /// The instant the standup day starts. When the rollover time does not
+    /// exist on that day, because the clocks went forward over it, the day
+    /// starts when they did; when it happens twice, it starts the first time.
+    pub fn start_of(&self, date: NaiveDate) -> DateTime<Utc> {
+        let start = date.and_time(self.rollover);
+        match self.zone {
+            Some(zone) => first_instant(&zone, start),
+            None => first_instant(&Local, start),
+        }
+    }
+
+    pub fn now(&self) -> NaiveDateTime {
+        self.local(Utc::now())
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.day(Utc::now())
+    }
+}
+
+fn first_instant<Z: TimeZone>(zone: &Z, local: NaiveDateTime) -> DateTime<Utc> {
+    // A gap in the clock is never longer than a day, so this always ends.
+    let mut local = local;
+    loop {
+        if let Some(at) = zone.from_local_datetime(&local).earliest() {
+            return at.with_timezone(&Utc);
+        }
+        local = local + Duration::minutes(1);
+    }
+}
+
+/// Reads a zone by its tz database name, such as `Europe/Berlin`.
+pub fn parse_zone(name: &str) -> Option<Tz> {
+    name.parse().ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn clock(zone: &str, rollover: &str) -> Clock {
+        Clock {
+            zone: parse_zone(zone),
+            rollover: NaiveTime::parse_from_str(rollover, "%H:%M").unwrap(),
+        }
+    }
+
+    fn utc(at: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(at).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn it_will_count_the_day_in_the_zone() {
+        let clock = clock("America/Los_Angeles", "00:00");
+        assert_eq!(clock.day(utc("2016-03-08T03:00:00Z")), NaiveDate::from_ymd_opt(2016, 3, 7).unwrap());
+        assert_eq!(clock.day(utc("2016-03-08T08:00:00Z")), NaiveDate::from_ymd_opt(2016, 3, 8).unwrap());
+        assert_eq!(parse_zone("Mars/Olympus_Mons"), None);
+    }
+
+    #[test]
+    fn it_will_roll_over_at_the_configured_time() {
+        let clock = clock("Europe/Berlin", "04:00");
+        assert_eq!(clock.day(utc("2016-03-07T23:30:00Z")), NaiveDate::from_ymd_opt(2016, 3, 7).unwrap());
+        assert_eq!(clock.day(utc("2016-03-08T02:59:59Z")), NaiveDate::from_ymd_opt(2016, 3, 7).unwrap());
+        assert_eq!(clock.day(utc("2016-03-08T03:00:00Z")), NaiveDate::from_ymd_opt(2016, 3, 8).unwrap());
+        assert_eq!(clock.start_of(NaiveDate::from_ymd_opt(2016, 3, 8).unwrap()), utc("2016-03-08T03:00:00Z"));
+    }
+
+    #[test]
+    fn it_will_roll_over_on_the_wall_clock_across_dst() {
+        let clock = clock("America/New_York", "04:00");
+        // The clocks went forward at 02:00 on 2016-03-13, so 04:00 came an hour early.
+        assert_eq!(clock.day(utc("2016-03-13T07:59:00Z")), NaiveDate::from_ymd_opt(2016, 3, 12).unwrap());
+        assert_eq!(clock.day(utc("2016-03-13T08:00:00Z")), NaiveDate::from_ymd_opt(2016, 3, 13).unwrap());
+        assert_eq!(clock.start_of(NaiveDate::from_ymd_opt(2016, 3, 13).unwrap()), utc("2016-03-13T08:00:00Z"));
+        // And back at 02:00 on 2016-11-06, so 01:30 happened twice and 04:00 came an hour late.
+        assert_eq!(clock.day(utc("2016-11-06T05:30:00Z")), NaiveDate::from_ymd_opt(2016, 11, 5).unwrap());
+        assert_eq!(clock.day(utc("2016-11-06T06:30:00Z")), NaiveDate::from_ymd_opt(2016, 11, 5).unwrap());
+        assert_eq!(clock.day(utc("2016-11-06T08:59:00Z")), NaiveDate::from_ymd_opt(2016, 11, 5).unwrap());
+        assert_eq!(clock.day(utc("2016-11-06T09:00:00Z")), NaiveDate::from_ymd_opt(2016, 11, 6).unwrap());
+    }
+
+    #[test]
+    fn it_will_start_days_at_the_edges_of_dst() {
+        let skipped = clock("America/New_York", "02:30");
+        assert_eq!(skipped.start_of(NaiveDate::from_ymd_opt(2016, 3, 13).unwrap()), utc("2016-03-13T07:00:00Z"));
+        assert_eq!(skipped.day(utc("2016-03-13T06:59:00Z")), NaiveDate::from_ymd_opt(2016, 3, 12).unwrap());
+        assert_eq!(skipped.day(utc("2016-03-13T07:00:00Z")), NaiveDate::from_ymd_opt(2016, 3, 13).unwrap());
+        let repeated = clock("America/New_York", "01:30");
+        assert_eq!(repeated.start_of(NaiveDate::from_ymd_opt(2016, 11, 6).unwrap()), utc("2016-11-06T05:30:00Z"));
+    }
+}
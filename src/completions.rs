@@ -0,0 +1,178 @@
+use clap;
+use clap::Shell;
+use standup::Aspect;
+
+pub static SHELLS: &'static [&'static str] = &["bash", "zsh", "fish"];
+
+/// Options that take a value, so the word after them is not a positional.
+static VALUED: &'static [&'static str] = &["--config", "--profile", "--color", "--output", "-d", "--date",
+                                           "--as", "--to-type", "--to-line", "--to-date"];
+
+/// What the word being completed stands for, when it depends on the standups
+/// rather than on the command line definition.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Wanted {
+    /// A date that has a standup.
+    Date,
+    /// A line of the aspect on the date, or the app's date when none is given.
+    Line { aspect: Aspect, date: Option<String>, author: Option<String> },
+}
+
+/// Works out what is wanted from the words before the one being completed,
+/// starting with the program name.
+pub fn wanted(words: &[String]) -> Option<Wanted> {
+    let last = match words.last() {
+        Some(last) => last.as_str(),
+        None => return None,
+    };
+    if last == "-d" || last == "--date" || last == "--to-date" {
+        return Some(Wanted::Date);
+    }
+
+    let mut positionals = vec![];
+    let mut date = None;
+    let mut author = None;
+    let mut words = words.iter().skip(1);
+    while let Some(word) = words.next() {
+        if VALUED.contains(&word.as_str()) {
+            let value = words.next().cloned();
+            match word.as_str() {
+                "-d" | "--date" => date = value,
+                "--as" => author = value,
+                _ => {},
+            }
+        } else if word.starts_with("--date=") {
+            date = Some(word["--date=".len()..].to_string());
+        } else if word.starts_with("--as=") {
+            author = Some(word["--as=".len()..].to_string());
+        } else if !word.starts_with("-") {
+            positionals.push(word.as_str());
+        }
+    }
+    match positionals.as_slice() {
+        [command, aspect] if ["delete", "d", "move", "mv"].contains(command) => {
+            Aspect::from_name(aspect).map(|aspect| Wanted::Line { aspect: aspect, date: date, author: author })
+        },
+        _ => None,
+    }
+}
+
+/// A value as the shell's hook expects it, with a description where the
+/// shell can show one.
+pub fn candidate(shell: Shell, value: &str, description: &str) -> String {
+    let description: String = if description.chars().count() > 50 {
+        description.chars().take(49).chain("…".chars()).collect()
+    } else {
+        description.to_string()
+    };
+    match shell {
+        Shell::Zsh => format!("{}:{}", value.replace(":", "\\:"), description),
+        Shell::Fish => format!("{}\t{}", value, description),
+        _ => value.to_string(),
+    }
+}
+
+/// The completion script for the shell. Before falling back to what clap
+/// generates, it asks `standup complete` for dates and line numbers.
+pub fn script(cli: clap::App, shell: Shell) -> String {
+    let mut cli = cli;
+    let mut out = vec![];
+    cli.gen_completions_to("standup", shell, &mut out);
+    let generated = String::from_utf8_lossy(&out).into_owned();
+    match shell {
+        Shell::Bash => generated + BASH_HOOK,
+        Shell::Zsh => {
+            let generated = generated.replace("\n_standup() {", "\n_standup_commandline() {");
+            let end = generated.rfind("_standup \"$@\"").unwrap_or(generated.len());
+            generated[..end].to_string() + ZSH_HOOK
+        },
+        Shell::Fish => generated + FISH_HOOK,
+        _ => generated,
+    }
+}
+
+static BASH_HOOK: &'static str = r#"
+_standup_dynamic() {
+    local values
+    values="$(standup complete bash -- "${COMP_WORDS[@]:0:COMP_CWORD}" 2>/dev/null)"
+    if [[ -n "${values}" ]] ; then
+        COMPREPLY=( $(compgen -W "${values}" -- "${COMP_WORDS[COMP_CWORD]}") )
+        return 0
+    fi
+    _standup "$@"
+}
+
+complete -F _standup_dynamic -o bashdefault -o default standup
+"#;
+
+static ZSH_HOOK: &'static str = r#"_standup() {
+    local -a values
+    values=("${(@f)$(standup complete zsh -- "${(@)words[1,CURRENT-1]}" 2>/dev/null)}")
+    if [[ -n "${values[1]}" ]]; then
+        _describe -t values 'standup values' values
+        return
+    fi
+    _standup_commandline "$@"
+}
+
+_standup "$@"
+"#;
+
+static FISH_HOOK: &'static str = r#"
+function __standup_dynamic
+    set -l values (standup complete fish -- (commandline -opc) 2>/dev/null)
+    test (count $values) -gt 0; or return 1
+    printf '%s\n' $values
+end
+complete -c standup -f -n '__standup_dynamic >/dev/null' -a '(__standup_dynamic)'
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::{App, Arg, SubCommand, Shell};
+    use standup::Aspect;
+
+    fn words(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn it_will_want_dates_and_line_numbers() {
+        assert_eq!(wanted(&words("standup today -d")), Some(Wanted::Date));
+        assert_eq!(wanted(&words("standup --profile work delete --as kim -d 2016-03-04 blocker")),
+                   Some(Wanted::Line {
+                       aspect: Aspect::Blocker,
+                       date: Some("2016-03-04".to_string()),
+                       author: Some("kim".to_string()),
+                   }));
+        assert_eq!(wanted(&words("standup mv today")),
+                   Some(Wanted::Line { aspect: Aspect::Today, date: None, author: None }));
+        assert_eq!(wanted(&words("standup --output json --color never delete today")),
+                   Some(Wanted::Line { aspect: Aspect::Today, date: None, author: None }));
+        assert_eq!(wanted(&words("standup delete today 1")), None);
+        assert_eq!(wanted(&words("standup today")), None);
+    }
+
+    #[test]
+    fn it_will_describe_candidates_where_the_shell_can() {
+        assert_eq!(candidate(Shell::Bash, "1", "review"), "1");
+        assert_eq!(candidate(Shell::Zsh, "1", "review: the migration"), "1:review: the migration");
+        assert_eq!(candidate(Shell::Fish, "1", &"x".repeat(60)), format!("1\t{}…", "x".repeat(49)));
+    }
+
+    #[test]
+    fn it_will_hook_the_dynamic_values_into_each_script() {
+        let cli = || App::new("standup")
+            .subcommand(SubCommand::with_name("today").arg(Arg::with_name("date").long("date").takes_value(true)));
+        let bash = script(cli(), Shell::Bash);
+        assert!(bash.contains("complete -F _standup_dynamic"));
+        let zsh = script(cli(), Shell::Zsh);
+        assert!(zsh.contains("_standup_commandline() {"));
+        assert!(zsh.trim_end().ends_with("_standup \"$@\""));
+        assert_eq!(zsh.matches("_standup \"$@\"").count(), 1);
+        let fish = script(cli(), Shell::Fish);
+        assert!(fish.contains("__fish_seen_subcommand_from today"));
+        assert!(fish.contains("standup complete fish"));
+    }
+}
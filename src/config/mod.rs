@@ -0,0 +1,697 @@
+mod profile;
+
+use std::path::{Path, PathBuf};
+use std::env;
+use std::env::home_dir;
+use std::fs;
+use std::io::{Read, Write};
+use std::collections::BTreeMap;
+use chrono::{Weekday, NaiveDate};
+use toml;
+use toml::Value;
+use toml::value::Table;
+use serde_json;
+use ::CliError;
+use ::StandupError;
+use standup::Aspect;
+
+pub use self::profile::{Profile, format_range};
+use self::profile::{PROFILE_KEYS, is_valid_name};
+
+pub static EXPORT_FORMATS: &'static [&'static str] = &["csv", "yaml", "ics"];
+
+/// What to bring forward from the previous workday when a new day's standup
+/// is started.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CarryOver {
+    /// Start every day empty.
+    Off,
+    /// Yesterday's "today" entries become this day's "yesterday".
+    Yesterday,
+    /// As above, and unresolved blockers are carried along too.
+    All,
+}
+
+impl CarryOver {
+    pub fn from_name(name: &str) -> Option<CarryOver> {
+        match name {
+            "off" => Some(CarryOver::Off),
+            "yesterday" => Some(CarryOver::Yesterday),
+            "all" => Some(CarryOver::All),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            CarryOver::Off => "off",
+            CarryOver::Yesterday => "yesterday",
+            CarryOver::All => "all",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Text,
+    List,
+}
+
+/// The settings that only make sense once for the whole file. Everything in
+/// `PROFILE_KEYS` may also be set here, as the default for every profile.
+static KEYS: &'static [(&'static str, Kind)] = &[
+    ("profile", Kind::Text),
+    ("export.format", Kind::Text),
+];
+
+///
+/// ## Config
+///
+/// Preferences loaded from `$XDG_CONFIG_HOME/standup/config.toml`. Anything
+/// left out of the file falls back to how standup has always behaved.
+///
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub file: PathBuf,
+    pub current: String,
+    pub profiles: BTreeMap<String, Profile>,
+    pub export_format: String,
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Reads the config from the given file, or the default location. A
+    /// missing file just means every setting has its default.
+    pub fn load(file: Option<&str>) -> Result<Config, CliError> {
+        let file = match file {
+            Some(file) => PathBuf::from(file),
+            None => try!(Config::default_file()),
+        };
+        let root = try!(Config::read_table(&file));
+        Config::from_table(file, &root)
+    }
+
+    pub fn default_file() -> Result<PathBuf, CliError> {
+        let base = match env::var_os("XDG_CONFIG_HOME") {
+            Some(ref dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => try!(home()).join(".config"),
+        };
+        Ok(base.join("standup").join("config.toml"))
+    }
+
+    fn read_table(file: &Path) -> Result<Table, CliError> {
+        if !file.is_file() {
+            return Ok(Table::new());
+        }
+        let mut buf = String::new();
+        try!(fs::File::open(file).and_then(|mut f| f.read_to_string(&mut buf)).map_err(CliError::Io));
+        buf.parse::<Value>()
+            .map_err(CliError::Toml)
+            .map(|value| value.as_table().cloned().unwrap_or(Table::new()))
+    }
+
+    pub fn from_table(file: PathBuf, root: &Table) -> Result<Config, CliError> {
+        try!(Config::check_keys(root, ""));
+
+        let default = try!(Profile::from_table("default", root, None, ""));
+        let mut profiles = BTreeMap::new();
+        if let Some(table) = root.get("profiles") {
+            let table = try!(table.as_table().ok_or(invalid("", "profiles", "must be a table")));
+            for (name, settings) in table.iter() {
+                let prefix = format!("profiles.{}.", name);
+                if !is_valid_name(name) || name == "default" {
+                    return Err(invalid(&prefix, "", "is not a valid profile name"));
+                }
+                let settings = try!(settings.as_table().ok_or(invalid(&prefix, "", "must be a table")));
+                try!(Config::check_profile_keys(settings, &prefix, ""));
+                profiles.insert(name.clone(), try!(Profile::from_table(name, settings, Some(&default), &prefix)));
+            }
+        }
+        profiles.insert("default".to_string(), default);
+
+        let current = try!(text(root, "profile", "")).unwrap_or("default".to_string());
+        if !profiles.contains_key(&current) {
+            return Err(invalid("", "profile", "names a profile that does not exist"));
+        }
+
+        let export_format = try!(text(root, "export.format", "")).unwrap_or("csv".to_string());
+        if !EXPORT_FORMATS.contains(&export_format.as_str()) {
+            return Err(invalid("", "export.format", "must be one of csv, yaml or ics"));
+        }
+
+        let mut aliases = BTreeMap::new();
+        if let Some(table) = root.get("aliases") {
+            let table = try!(table.as_table().ok_or(invalid("", "aliases", "must be a table")));
+            for (name, value) in table.iter() {
+                let key = format!("aliases.{}", name);
+                let expansion = try!(value.as_str().ok_or(invalid("", &key, "must be a string")));
+                if expansion.trim().is_empty() {
+                    return Err(invalid("", &key, "must not be empty"));
+                }
+                aliases.insert(name.clone(), expansion.to_string());
+            }
+        }
+
+        Ok(Config {
+            file: file,
+            current: current,
+            profiles: profiles,
+            export_format: export_format,
+            aliases: aliases,
+        })
+    }
+
+    /// Complains about the first key that is not a known setting.
+    fn check_keys(table: &Table, prefix: &str) -> Result<(), CliError> {
+        for (name, value) in table.iter() {
+            let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+            if key == "aliases" || key == "profiles" || KEYS.iter().any(|&(known, _)| known == key) {
+                continue;
+            }
+            if PROFILE_KEYS.iter().any(|&(known, _)| known == key) {
+                continue;
+            }
+            let is_section = KEYS.iter().chain(PROFILE_KEYS.iter())
+                .any(|&(known, _)| known.starts_with(&format!("{}.", key)));
+            match value.as_table() {
+                Some(section) if is_section => try!(Config::check_keys(section, &key)),
+                _ => return Err(invalid("", &key, "is not a known setting")),
+            }
+        }
+        Ok(())
+    }
+
+    fn check_profile_keys(table: &Table, profile: &str, prefix: &str) -> Result<(), CliError> {
+        for (name, value) in table.iter() {
+            let key = if prefix.is_empty() { name.clone() } else { format!("{}.{}", prefix, name) };
+            if PROFILE_KEYS.iter().any(|&(known, _)| known == key) {
+                continue;
+            }
+            let is_section = PROFILE_KEYS.iter().any(|&(known, _)| known.starts_with(&format!("{}.", key)));
+            match value.as_table() {
+                Some(section) if is_section => try!(Config::check_profile_keys(section, profile, &key)),
+                _ => return Err(invalid(profile, &key, "is not a known setting")),
+            }
+        }
+        Ok(())
+    }
+
+    /// The profile to use: the one asked for, or else the one the config
+    /// file has switched to.
+    pub fn profile(&self, name: Option<&str>) -> Result<&Profile, CliError> {
+        let name = name.unwrap_or(&self.current);
+        self.profiles.get(name).ok_or(CliError::Cli(StandupError::ProfileNotFound))
+    }
+
+    /// Every setting and its current value, including the defaults.
+    pub fn list(&self) -> Vec<(String, Value)> {
+        let mut settings: Vec<(String, Value)> = KEYS.iter()
+            .chain(PROFILE_KEYS.iter())
+            .filter_map(|&(key, _)| self.get(key).map(|value| (key.to_string(), value)))
+            .collect();
+        for (name, profile) in self.profiles.iter().filter(|&(name, _)| name != "default") {
+            for &(key, _) in PROFILE_KEYS.iter() {
+                if let Some(value) = profile.get(key) {
+                    settings.push((format!("profiles.{}.{}", name, key), value));
+                }
+            }
+        }
+        for (name, expansion) in self.aliases.iter() {
+            settings.push((format!("aliases.{}", name), Value::String(expansion.clone())));
+        }
+        settings
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        match key {
+            "profile" => Some(Value::String(self.current.clone())),
+            "export.format" => Some(Value::String(self.export_format.clone())),
+            _ if key.starts_with("aliases.") => {
+                self.aliases.get(&key["aliases.".len()..]).map(|e| Value::String(e.clone()))
+            },
+            _ if key.starts_with("profiles.") => {
+                let mut parts = key.splitn(3, '.').skip(1);
+                match (parts.next(), parts.next()) {
+                    (Some(name), Some(setting)) => self.profiles.get(name).and_then(|p| p.get(setting)),
+                    _ => None,
+                }
+            },
+            _ => self.profiles.get("default").and_then(|profile| profile.get(key)),
+        }
+    }
+
+    fn kind(key: &str) -> Option<Kind> {
+        let find = |keys: &[(&str, Kind)], key: &str| {
+            keys.iter().find(|&&(known, _)| known == key).map(|&(_, kind)| kind)
+        };
+        if key.starts_with("aliases.") && key.len() > "aliases.".len() {
+            return Some(Kind::Text);
+        }
+        if key.starts_with("profiles.") {
+            let parts: Vec<&str> = key.splitn(3, '.').collect();
+            return if parts.len() == 3 { find(PROFILE_KEYS, parts[2]) } else { None };
+        }
+        find(KEYS, key).or(find(PROFILE_KEYS, key))
+    }
+
+    /// Writes one setting to the config file, leaving the rest of the file as
+    /// it was. Lists are given comma separated. Nothing is written if the new
+    /// value does not validate.
+    pub fn set(&self, key: &str, raw: &str) -> Result<Config, CliError> {
+        let kind = try!(Config::kind(key).ok_or(invalid("", key, "is not a known setting")));
+        let value = match kind {
+            Kind::Text => Value::String(raw.to_string()),
+            Kind::List => Value::Array(raw.split(',')
+                                       .map(|item| item.trim())
+                                       .filter(|item| !item.is_empty())
+                                       .map(|item| Value::String(item.to_string()))
+                                       .collect()),
+        };
+        self.update(|root| {
+            let mut table = root;
+            let parts: Vec<&str> = key.split('.').collect();
+            for part in parts[..parts.len() - 1].iter() {
+                let entry = table.entry(part.to_string()).or_insert(Value::Table(Table::new()));
+                table = try!(entry.as_table_mut().ok_or(invalid("", part, "must be a table")));
+            }
+            table.insert(parts[parts.len() - 1].to_string(), value);
+            Ok(())
+        })
+    }
+
+    /// Adds a profile with its own data file, which is written into the
+    /// config so that renaming the profile later does not lose track of it.
+    pub fn create_profile(&self, name: &str, data_path: Option<&str>) -> Result<Config, CliError> {
+        if !is_valid_name(name) {
+            return Err(invalid("profiles.", name, "is not a valid profile name"));
+        }
+        if self.profiles.contains_key(name) {
+            return Err(CliError::Cli(StandupError::ProfileExists));
+        }
+        let path = match data_path {
+            Some(path) => path.to_string(),
+            None => try!(Profile::default_data_path(name)).to_string_lossy().into_owned(),
+        };
+        self.set(&format!("profiles.{}.data.path", name), &path)
+    }
+
+    /// Adds time off to the profile's `calendar.pto`.
+    pub fn add_pto(&self, name: &str, from: NaiveDate, to: NaiveDate) -> Result<Config, CliError> {
+        let profile = try!(self.profile(Some(name)));
+        let mut pto = profile.pto.clone();
+        pto.push((from, to));
+        let key = if name == "default" { "calendar.pto".to_string() } else { format!("profiles.{}.calendar.pto", name) };
+        self.set(&key, &pto.iter().map(format_range).collect::<Vec<String>>().join(", "))
+    }
+
+    /// Makes the profile the one used when `--profile` is not given.
+    pub fn switch_profile(&self, name: &str) -> Result<Config, CliError> {
+        try!(self.profile(Some(name)));
+        self.set("profile", name)
+    }
+
+    /// Renames the profile, keeping its data where it is.
+    pub fn rename_profile(&self, from: &str, to: &str) -> Result<Config, CliError> {
+        if from == "default" || to == "default" {
+            return Err(CliError::Cli(StandupError::DefaultProfile));
+        }
+        let data_path = try!(self.profile(Some(from))).data_path.to_string_lossy().into_owned();
+        if !is_valid_name(to) {
+            return Err(invalid("profiles.", to, "is not a valid profile name"));
+        }
+        if self.profiles.contains_key(to) {
+            return Err(CliError::Cli(StandupError::ProfileExists));
+        }
+        let current = self.current.clone();
+        self.update(|root| {
+            let mut settings = Config::profile_table(root, from);
+            let mut data = Table::new();
+            data.insert("path".to_string(), Value::String(data_path));
+            settings.entry("data".to_string()).or_insert(Value::Table(data));
+            Config::profiles_table(root).insert(to.to_string(), Value::Table(settings));
+            if current == from {
+                root.insert("profile".to_string(), Value::String(to.to_string()));
+            }
+            Ok(())
+        })
+    }
+
+    /// Forgets the profile. Its data file is left alone.
+    pub fn delete_profile(&self, name: &str) -> Result<Config, CliError> {
+        if name == "default" {
+            return Err(CliError::Cli(StandupError::DefaultProfile));
+        }
+        try!(self.profile(Some(name)));
+        let current = self.current.clone();
+        self.update(|root| {
+            Config::profile_table(root, name);
+            if Config::profiles_table(root).is_empty() {
+                root.remove("profiles");
+            }
+            if current == name {
+                root.remove("profile");
+            }
+            Ok(())
+        })
+    }
+
+    fn profiles_table(root: &mut Table) -> &mut Table {
+        let entry = root.entry("profiles".to_string()).or_insert(Value::Table(Table::new()));
+        if entry.as_table().is_none() {
+            *entry = Value::Table(Table::new());
+        }
+        entry.as_table_mut().unwrap()
+    }
+
+    /// Takes the profile's table out of the file.
+    fn profile_table(root: &mut Table, name: &str) -> Table {
+        Config::profiles_table(root)
+            .remove(name)
+            .and_then(|settings| settings.as_table().cloned())
+            .unwrap_or(Table::new())
+    }
+
+    /// Changes the file's contents and writes them back, but only if the
+    /// result is still a valid config.
+    fn update<F>(&self, change: F) -> Result<Config, CliError>
+        where F: FnOnce(&mut Table) -> Result<(), CliError>
+    {
+        let mut root = try!(Config::read_table(&self.file));
+        try!(change(&mut root));
+        let config = try!(Config::from_table(self.file.clone(), &root));
+
+        let contents = try!(toml::to_string(&Value::Table(root))
+                            .map_err(|err| invalid("", "config", &err.to_string())));
+        if let Some(dir) = self.file.parent() {
+            try!(fs::create_dir_all(dir).map_err(CliError::Io));
+        }
+        try!(fs::File::create(&self.file)
+             .and_then(|mut file| file.write_all(contents.as_bytes()))
+             .map_err(CliError::Io));
+        Ok(config)
+    }
+
+    /// Replaces the first word of the arguments with its alias, if it has
+    /// one. Aliases are only expanded once, so they cannot loop.
+    pub fn expand_alias(&self, args: Vec<String>) -> Vec<String> {
+        let mut skip_value = false;
+        let position = args.iter().enumerate().skip(1).position(|(_, arg)| {
+            if skip_value {
+                skip_value = false;
+                return false;
+            }
+            if ["--config", "--profile", "--color", "--output"].contains(&arg.as_str()) {
+                skip_value = true;
+                return false;
+            }
+            !arg.starts_with("-")
+        }).map(|index| index + 1);
+
+        match position.and_then(|index| self.aliases.get(&args[index]).map(|e| (index, e))) {
+            Some((index, expansion)) => {
+                let mut expanded: Vec<String> = args[..index].to_vec();
+                expanded.extend(expansion.split_whitespace().map(|word| word.to_string()));
+                expanded.extend_from_slice(&args[index + 1..]);
+                expanded
+            },
+            None => args,
+        }
+    }
+}
+
+/// Finds `--name VALUE` or `--name=VALUE` in the raw arguments, which has to
+/// happen before they are parsed so that aliases can be expanded.
+pub fn find_arg(args: &[String], name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    for (i, arg) in args.iter().enumerate() {
+        if arg == name {
+            return args.get(i + 1).cloned();
+        }
+        if arg.starts_with(&prefix) {
+            return Some(arg[prefix.len()..].to_string());
+        }
+    }
+    None
+}
+
+/// Settings as JSON, for `--output json`.
+pub fn to_json(value: &Value) -> serde_json::Value {
+    match *value {
+        Value::String(ref text) => serde_json::Value::String(text.clone()),
+        Value::Integer(number) => serde_json::Value::I64(number),
+        Value::Float(number) => serde_json::Value::F64(number),
+        Value::Boolean(flag) => serde_json::Value::Bool(flag),
+        Value::Datetime(ref datetime) => serde_json::Value::String(datetime.to_string()),
+        Value::Array(ref items) => serde_json::Value::Array(items.iter().map(to_json).collect()),
+        Value::Table(ref table) => serde_json::Value::Object(
+            table.iter().map(|(key, value)| (key.clone(), to_json(value))).collect()),
+    }
+}
+
+pub fn weekday_from_name(name: &str) -> Option<Weekday> {
+    match &name.to_lowercase()[..] {
+        "mon" | "monday" => Some(Weekday::Mon),
+        "tue" | "tuesday" => Some(Weekday::Tue),
+        "wed" | "wednesday" => Some(Weekday::Wed),
+        "thu" | "thursday" => Some(Weekday::Thu),
+        "fri" | "friday" => Some(Weekday::Fri),
+        "sat" | "saturday" => Some(Weekday::Sat),
+        "sun" | "sunday" => Some(Weekday::Sun),
+        _ => None
+    }
+}
+
+fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "mon",
+        Weekday::Tue => "tue",
+        Weekday::Wed => "wed",
+        Weekday::Thu => "thu",
+        Weekday::Fri => "fri",
+        Weekday::Sat => "sat",
+        Weekday::Sun => "sun",
+    }
+}
+
+fn home() -> Result<PathBuf, CliError> {
+    home_dir().ok_or(CliError::Cli(StandupError::HomeDirNotFound))
+}
+
+fn expand(path: &str) -> Result<PathBuf, CliError> {
+    if path.starts_with("~/") {
+        Ok(try!(home()).join(&path[2..]))
+    } else {
+        Ok(PathBuf::from(path))
+    }
+}
+
+/// An error naming the full key, `prefix` being where the table the key was
+/// read from sits in the file.
+fn invalid(prefix: &str, key: &str, reason: &str) -> CliError {
+    let full = format!("{}{}", prefix, key);
+    CliError::Cli(StandupError::InvalidConfig(full.trim_end_matches('.').to_string(), reason.to_string()))
+}
+
+fn lookup<'a>(root: &'a Table, key: &str) -> Option<&'a Value> {
+    let mut parts = key.split('.');
+    let first = parts.next().and_then(|part| root.get(part));
+    parts.fold(first, |value, part| value.and_then(|v| v.get(part)))
+}
+
+fn text(root: &Table, key: &str, prefix: &str) -> Result<Option<String>, CliError> {
+    match lookup(root, key) {
+        Some(value) => value.as_str()
+            .map(|s| Some(s.to_string()))
+            .ok_or(invalid(prefix, key, "must be a string")),
+        None => Ok(None),
+    }
+}
+
+fn list(root: &Table, key: &str, prefix: &str) -> Result<Option<Vec<String>>, CliError> {
+    match lookup(root, key) {
+        Some(value) => {
+            let items = try!(value.as_array().ok_or(invalid(prefix, key, "must be a list of strings")));
+            let mut strings = vec![];
+            for item in items {
+                strings.push(try!(item.as_str().ok_or(invalid(prefix, key, "must be a list of strings"))).to_string());
+            }
+            Ok(Some(strings))
+        },
+        None => Ok(None),
+    }
+}
+
+fn aspects(root: &Table, key: &str, prefix: &str) -> Result<Option<Vec<Aspect>>, CliError> {
+    match try!(list(root, key, prefix)) {
+        Some(names) => {
+            let mut aspects = vec![];
+            for name in names.iter() {
+                aspects.push(try!(Aspect::from_name(name)
+                                  .ok_or(invalid(prefix, key, "must only contain today, yesterday or blocker"))));
+            }
+            Ok(Some(aspects))
+        },
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::CliError;
+    use ::StandupError;
+    use standup::{Aspect, Layout};
+    use store::test::scratch;
+    use std::path::PathBuf;
+    use chrono::Weekday;
+    use toml::Value;
+
+    fn parse(toml: &str) -> Result<Config, CliError> {
+        let root = toml.parse::<Value>().unwrap();
+        Config::from_table(PathBuf::from("config.toml"), root.as_table().unwrap())
+    }
+
+    fn offending_key(result: Result<Config, CliError>) -> String {
+        match result {
+            Err(CliError::Cli(StandupError::InvalidConfig(key, _))) => key,
+            other => panic!("expected an invalid config, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn it_will_default_everything() {
+        let config = parse("").unwrap();
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.name, "default");
+        assert!(profile.data_path.ends_with(".standup.json"));
+        assert_eq!(profile.layout, Layout::default());
+        assert_eq!(profile.workweek.len(), 5);
+        assert_eq!(profile.carry_over, CarryOver::Off);
+        assert_eq!(config.export_format, "csv");
+    }
+
+    #[test]
+    fn it_can_read_every_setting() {
+        let config = parse(r#"
+            workweek = ["sun", "mon", "tue", "wed", "thu"]
+            carry_over = "all"
+
+            [data]
+            path = "/tmp/standup.json"
+
+            [display]
+            date_format = "%d.%m.%Y"
+            order = ["blocker", "today"]
+            hide_empty = []
+            pager = "more"
+
+            [export]
+            format = "ics"
+
+            [aliases]
+            td = "today --date"
+        "#).unwrap();
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.data_path, PathBuf::from("/tmp/standup.json"));
+        assert_eq!(profile.layout.date_format, "%d.%m.%Y");
+        assert_eq!(profile.layout.order, vec![Aspect::Blocker, Aspect::Today]);
+        assert_eq!(profile.layout.hide_empty.len(), 0);
+        assert_eq!(profile.pager, Some("more".to_string()));
+        assert_eq!(profile.workweek[0], Weekday::Sun);
+        assert_eq!(profile.carry_over, CarryOver::All);
+        assert_eq!(config.export_format, "ics");
+        assert_eq!(config.aliases.get("td").unwrap(), "today --date");
+    }
+
+    #[test]
+    fn it_will_point_at_the_offending_key() {
+        assert_eq!(offending_key(parse("[display]\norder = [\"tomorrow\"]")), "display.order");
+        assert_eq!(offending_key(parse("[display]\ndate_format = 3")), "display.date_format");
+        assert_eq!(offending_key(parse("[display]\ndate_format = \"%Q\"")), "display.date_format");
+        assert_eq!(offending_key(parse("workweek = [\"someday\"]")), "workweek");
+        assert_eq!(offending_key(parse("carry_over = \"sometimes\"")), "carry_over");
+        assert_eq!(offending_key(parse("[export]\nformat = \"pdf\"")), "export.format");
+        assert_eq!(offending_key(parse("[display]\ncolour = \"red\"")), "display.colour");
+        assert_eq!(offending_key(parse("[aliases]\nt = 1")), "aliases.t");
+        assert_eq!(offending_key(parse("profile = \"guild\"")), "profile");
+        assert_eq!(offending_key(parse("[profiles.guild.display]\norder = [1]")), "profiles.guild.display.order");
+        assert_eq!(offending_key(parse("[profiles.guild.export]\nformat = \"csv\"")), "profiles.guild.export");
+    }
+
+    #[test]
+    fn it_can_set_a_value_and_keep_the_rest() {
+        let file = scratch("config-set").join("standup").join("config.toml");
+        let config = Config::load(file.to_str()).unwrap();
+        let config = config.set("display.order", "blocker, today, yesterday").unwrap();
+        let config = config.set("aliases.ls", "list").unwrap();
+        assert_eq!(config.profile(None).unwrap().layout.order[0], Aspect::Blocker);
+
+        let reloaded = Config::load(file.to_str()).unwrap();
+        assert_eq!(reloaded.profile(None).unwrap().layout.order,
+                   vec![Aspect::Blocker, Aspect::Today, Aspect::Yesterday]);
+        assert_eq!(reloaded.aliases.get("ls").unwrap(), "list");
+    }
+
+    #[test]
+    fn it_will_not_write_an_invalid_value() {
+        let file = scratch("config-invalid").join("config.toml");
+        let config = Config::load(file.to_str()).unwrap();
+        assert!(config.set("carry_over", "maybe").is_err());
+        assert!(config.set("unknown", "value").is_err());
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn it_can_get_values() {
+        let config = parse("carry_over = \"yesterday\"\n[profiles.guild]\ncarry_over = \"off\"").unwrap();
+        assert_eq!(config.get("carry_over"), Some(Value::String("yesterday".to_string())));
+        assert_eq!(config.get("profiles.guild.carry_over"), Some(Value::String("off".to_string())));
+        assert_eq!(format!("{}", config.get("display.order").unwrap()), "[\"today\", \"yesterday\", \"blocker\"]");
+        assert_eq!(config.get("nope"), None);
+    }
+
+    #[test]
+    fn it_can_manage_profiles() {
+        let dir = scratch("config-profiles");
+        let file = dir.join("config.toml");
+        let config = Config::load(file.to_str()).unwrap();
+        let guild = dir.join("guild.json");
+        let config = config.create_profile("guild", guild.to_str()).unwrap();
+        assert!(config.create_profile("guild", None).is_err());
+        let config = config.switch_profile("guild").unwrap();
+        assert_eq!(config.profile(None).unwrap().data_path, guild);
+
+        let config = config.rename_profile("guild", "architecture").unwrap();
+        assert_eq!(config.current, "architecture");
+        assert_eq!(config.profile(Some("architecture")).unwrap().data_path, guild);
+        assert!(config.profile(Some("guild")).is_err());
+
+        let config = config.delete_profile("architecture").unwrap();
+        assert_eq!(config.current, "default");
+        assert!(config.delete_profile("default").is_err());
+        assert_eq!(Config::load(file.to_str()).unwrap().profiles.len(), 1);
+    }
+
+    #[test]
+    fn it_can_expand_aliases() {
+        let config = parse("[aliases]\ntd = \"today --date 2016-01-01\"").unwrap();
+        let args = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<String>>();
+        assert_eq!(config.expand_alias(args(&["standup", "td", "hello"])),
+                   args(&["standup", "today", "--date", "2016-01-01", "hello"]));
+        assert_eq!(config.expand_alias(args(&["standup", "--config", "td", "td"])),
+                   args(&["standup", "--config", "td", "today", "--date", "2016-01-01"]));
+        assert_eq!(config.expand_alias(args(&["standup", "--color", "never", "td"])),
+                   args(&["standup", "--color", "never", "today", "--date", "2016-01-01"]));
+        assert_eq!(config.expand_alias(args(&["standup", "--output", "json", "td"])),
+                   args(&["standup", "--output", "json", "today", "--date", "2016-01-01"]));
+        assert_eq!(config.expand_alias(args(&["standup", "show"])), args(&["standup", "show"]));
+    }
+
+    #[test]
+    fn it_can_find_an_argument() {
+        let args = |words: &[&str]| words.iter().map(|w| w.to_string()).collect::<Vec<String>>();
+        assert_eq!(find_arg(&args(&["standup", "--config", "a.toml", "show"]), "--config"), Some("a.toml".to_string()));
+        assert_eq!(find_arg(&args(&["standup", "show", "--profile=guild"]), "--profile"), Some("guild".to_string()));
+        assert_eq!(find_arg(&args(&["standup", "show"]), "--config"), None);
+    }
+}
@@ -0,0 +1,422 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+use chrono::{Weekday, NaiveTime, NaiveDate};
+use chrono::format::{StrftimeItems, Item};
+use toml::Value;
+use toml::value::Table;
+use ::CliError;
+use standup::{Aspect, Layout};
+use chatify::Chat;
+use email::Security;
+use calendar::{self, Calendar, Holiday};
+use clock::{self, Clock};
+use super::{Kind, CarryOver, home, expand, invalid, text, list, aspects, weekday_from_name, weekday_name};
+
+/// The settings a profile may override. Anything a profile leaves out comes
+/// from the top of the file, except for the data path, so that two profiles
+/// never share a log by accident.
+pub static PROFILE_KEYS: &'static [(&'static str, Kind)] = &[
+    ("data.path", Kind::Text),
+    ("display.date_format", Kind::Text),
+    ("display.order", Kind::List),
+    ("display.hide_empty", Kind::List),
+    ("display.pager", Kind::Text),
+    ("workweek", Kind::List),
+    ("calendar.holidays", Kind::List),
+    ("calendar.ics", Kind::Text),
+    ("calendar.pto", Kind::List),
+    ("day.timezone", Kind::Text),
+    ("day.rollover", Kind::Text),
+    ("carry_over", Kind::Text),
+    ("team.identity", Kind::Text),
+    ("team.members", Kind::List),
+    ("team.deadline", Kind::Text),
+    ("post.webhook", Kind::Text),
+    ("post.chat", Kind::Text),
+    ("email.from", Kind::Text),
+    ("email.to", Kind::List),
+    ("email.server", Kind::Text),
+    ("email.security", Kind::Text),
+    ("email.username", Kind::Text),
+    ("git.repos", Kind::List),
+    ("git.author", Kind::Text),
+    ("sync.remote", Kind::Text),
+];
+
+///
+/// ## Profile
+///
+/// A separate log with its own data store and its own way of showing it, such
+/// as one for a team standup and another for a guild.
+///
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub data_path: PathBuf,
+    pub layout: Layout,
+    /// The pager long listings go through, or `off`. `$PAGER` when not set.
+    pub pager: Option<String>,
+    pub workweek: Vec<Weekday>,
+    pub holidays: Vec<Holiday>,
+    /// An iCalendar file with more holidays, read when the calendar is used.
+    pub holiday_file: Option<PathBuf>,
+    pub pto: Vec<(NaiveDate, NaiveDate)>,
+    /// When each standup day starts, and in which zone.
+    pub clock: Clock,
+    pub carry_over: CarryOver,
+    /// Who entries are recorded as when `--as` is not given.
+    pub identity: Option<String>,
+    pub members: Vec<String>,
+    /// The time of day by which every member should have posted.
+    pub deadline: Option<NaiveTime>,
+    /// The incoming webhook `post` sends the standup to.
+    pub webhook: Option<String>,
+    pub chat: Chat,
+    pub email_from: Option<String>,
+    pub email_to: Vec<String>,
+    /// The SMTP server `email` sends through, as `host:port`.
+    pub smtp_server: Option<String>,
+    pub smtp_security: Security,
+    pub smtp_username: Option<String>,
+    /// The repositories `import-git` looks for commits in.
+    pub git_repos: Vec<PathBuf>,
+    /// The email address commits are made under.
+    pub git_author: Option<String>,
+    /// The git remote `sync` pulls from and pushes to.
+    pub sync_remote: String,
+}
+
+impl Profile {
+    /// Where a profile keeps its standups when the config does not say.
+    pub fn default_data_path(name: &str) -> Result<PathBuf, CliError> {
+        let file = if name == "default" {
+            ".standup.json".to_string()
+        } else {
+            format!(".standup.{}.json", name)
+        };
+        Ok(try!(home()).join(file))
+    }
+
+    /// Reads the profile's settings out of its table. `prefix` is where the
+    /// table sits in the file, so errors can name the full key.
+    pub fn from_table(name: &str, table: &Table, inherit: Option<&Profile>, prefix: &str) -> Result<Profile, CliError> {
+        let defaults = Layout::default();
+        let layout = inherit.map_or(defaults, |profile| profile.layout.clone());
+
+        let data_path = match try!(text(table, "data.path", prefix)) {
+            Some(path) => try!(expand(&path)),
+            None => try!(Profile::default_data_path(name)),
+        };
+
+        let date_format = try!(text(table, "display.date_format", prefix)).unwrap_or(layout.date_format);
+        if StrftimeItems::new(&date_format).any(|item| item == Item::Error) {
+            return Err(invalid(prefix, "display.date_format", "is not a valid date format"));
+        }
+
+        let order = try!(aspects(table, "display.order", prefix)).unwrap_or(layout.order);
+        let mut seen = vec![];
+        for aspect in order.iter() {
+            if seen.contains(aspect) {
+                return Err(invalid(prefix, "display.order", "lists an aspect more than once"));
+            }
+            seen.push(*aspect);
+        }
+        let hide_empty = try!(aspects(table, "display.hide_empty", prefix)).unwrap_or(layout.hide_empty);
+        let pager = match try!(text(table, "display.pager", prefix)) {
+            Some(pager) => Some(pager),
+            None => inherit.and_then(|profile| profile.pager.clone()),
+        };
+
+        let workweek = match try!(list(table, "workweek", prefix)) {
+            Some(days) => {
+                let mut weekdays = vec![];
+                for day in days.iter() {
+                    weekdays.push(try!(weekday_from_name(day).ok_or(
+                        invalid(prefix, "workweek", "must only contain days like mon, tue or wednesday"))));
+                }
+                if weekdays.is_empty() {
+                    return Err(invalid(prefix, "workweek", "needs at least one day"));
+                }
+                weekdays
+            },
+            None => inherit.map_or(vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+                                   |profile| profile.workweek.clone()),
+        };
+
+        let holidays = match try!(list(table, "calendar.holidays", prefix)) {
+            Some(days) => {
+                let mut holidays = vec![];
+                for day in days.iter() {
+                    holidays.push(try!(calendar::parse_holiday(day).ok_or(
+                        invalid(prefix, "calendar.holidays", "must only contain dates like 2016-12-26, with an optional name"))));
+                }
+                holidays
+            },
+            None => inherit.map_or(vec![], |profile| profile.holidays.clone()),
+        };
+        let holiday_file = match try!(text(table, "calendar.ics", prefix)) {
+            Some(path) => Some(try!(expand(&path))),
+            None => inherit.and_then(|profile| profile.holiday_file.clone()),
+        };
+        let pto = match try!(list(table, "calendar.pto", prefix)) {
+            Some(ranges) => {
+                let mut pto = vec![];
+                for range in ranges.iter() {
+                    pto.push(try!(calendar::parse_range(range).ok_or(
+                        invalid(prefix, "calendar.pto", "must only contain dates or ranges like 2016-03-07..2016-03-18"))));
+                }
+                pto
+            },
+            None => inherit.map_or(vec![], |profile| profile.pto.clone()),
+        };
+
+        let zone = match try!(text(table, "day.timezone", prefix)) {
+            Some(name) => Some(try!(clock::parse_zone(&name).ok_or(
+                invalid(prefix, "day.timezone", "must be a zone like Europe/Berlin or America/New_York")))),
+            None => inherit.and_then(|profile| profile.clock.zone),
+        };
+        let rollover = match try!(text(table, "day.rollover", prefix)) {
+            Some(time) => try!(NaiveTime::parse_from_str(&time, "%H:%M").map_err(|_| {
+                invalid(prefix, "day.rollover", "must be a time like 04:00")
+            })),
+            None => inherit.map_or(Clock::system().rollover, |profile| profile.clock.rollover),
+        };
+
+        let carry_over = match try!(text(table, "carry_over", prefix)) {
+            Some(name) => try!(CarryOver::from_name(&name).ok_or(
+                invalid(prefix, "carry_over", "must be one of off, yesterday or all"))),
+            None => inherit.map_or(CarryOver::Off, |profile| profile.carry_over),
+        };
+
+        let identity = match try!(text(table, "team.identity", prefix)) {
+            Some(identity) => Some(identity),
+            None => inherit.and_then(|profile| profile.identity.clone()),
+        };
+        let members = match try!(list(table, "team.members", prefix)) {
+            Some(members) => members,
+            None => inherit.map_or(vec![], |profile| profile.members.clone()),
+        };
+        let deadline = match try!(text(table, "team.deadline", prefix)) {
+            Some(time) => Some(try!(NaiveTime::parse_from_str(&time, "%H:%M").map_err(|_| {
+                invalid(prefix, "team.deadline", "must be a time like 09:30")
+            }))),
+            None => inherit.and_then(|profile| profile.deadline),
+        };
+
+        let webhook = match try!(text(table, "post.webhook", prefix)) {
+            Some(url) => Some(url),
+            None => inherit.and_then(|profile| profile.webhook.clone()),
+        };
+        let chat = match try!(text(table, "post.chat", prefix)) {
+            Some(name) => try!(Chat::from_name(&name).ok_or(
+                invalid(prefix, "post.chat", "must be one of slack, teams or mattermost"))),
+            None => inherit.map_or(Chat::Slack, |profile| profile.chat),
+        };
+
+        let email_from = match try!(text(table, "email.from", prefix)) {
+            Some(from) => Some(from),
+            None => inherit.and_then(|profile| profile.email_from.clone()),
+        };
+        let email_to = match try!(list(table, "email.to", prefix)) {
+            Some(to) => to,
+            None => inherit.map_or(vec![], |profile| profile.email_to.clone()),
+        };
+        let smtp_server = match try!(text(table, "email.server", prefix)) {
+            Some(server) => Some(server),
+            None => inherit.and_then(|profile| profile.smtp_server.clone()),
+        };
+        let smtp_security = match try!(text(table, "email.security", prefix)) {
+            Some(name) => try!(Security::from_name(&name).ok_or(
+                invalid(prefix, "email.security", "must be one of starttls, tls or none"))),
+            None => inherit.map_or(Security::StartTls, |profile| profile.smtp_security),
+        };
+        let smtp_username = match try!(text(table, "email.username", prefix)) {
+            Some(username) => Some(username),
+            None => inherit.and_then(|profile| profile.smtp_username.clone()),
+        };
+
+        let git_repos = match try!(list(table, "git.repos", prefix)) {
+            Some(repos) => {
+                let mut paths = vec![];
+                for repo in repos.iter() {
+                    paths.push(try!(expand(repo)));
+                }
+                paths
+            },
+            None => inherit.map_or(vec![], |profile| profile.git_repos.clone()),
+        };
+        let git_author = match try!(text(table, "git.author", prefix)) {
+            Some(author) => Some(author),
+            None => inherit.and_then(|profile| profile.git_author.clone()),
+        };
+        let sync_remote = match try!(text(table, "sync.remote", prefix)) {
+            Some(remote) => remote,
+            None => inherit.map_or("origin".to_string(), |profile| profile.sync_remote.clone()),
+        };
+
+        Ok(Profile {
+            name: name.to_string(),
+            data_path: data_path,
+            layout: Layout { date_format: date_format, order: order, hide_empty: hide_empty },
+            pager: pager,
+            workweek: workweek,
+            holidays: holidays,
+            holiday_file: holiday_file,
+            pto: pto,
+            clock: Clock { zone: zone, rollover: rollover },
+            carry_over: carry_over,
+            identity: identity,
+            members: members,
+            deadline: deadline,
+            webhook: webhook,
+            chat: chat,
+            email_from: email_from,
+            email_to: email_to,
+            smtp_server: smtp_server,
+            smtp_security: smtp_security,
+            smtp_username: smtp_username,
+            git_repos: git_repos,
+            git_author: git_author,
+            sync_remote: sync_remote,
+        })
+    }
+
+    /// The workdays, with the holidays from `calendar.ics` added to the ones
+    /// in the config.
+    pub fn calendar(&self) -> Result<Calendar, CliError> {
+        let mut holidays = self.holidays.clone();
+        if let Some(ref path) = self.holiday_file {
+            let mut text = String::new();
+            try!(File::open(path).and_then(|mut file| file.read_to_string(&mut text)).map_err(CliError::Io));
+            holidays.extend(calendar::parse_ics(&text));
+        }
+        Ok(Calendar { workweek: self.workweek.clone(), holidays: holidays, pto: self.pto.clone() })
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        let names = |aspects: &Vec<Aspect>| {
+            Value::Array(aspects.iter().map(|a| Value::String(a.name().to_string())).collect())
+        };
+        match key {
+            "data.path" => Some(Value::String(self.data_path.to_string_lossy().into_owned())),
+            "display.date_format" => Some(Value::String(self.layout.date_format.clone())),
+            "display.order" => Some(names(&self.layout.order)),
+            "display.hide_empty" => Some(names(&self.layout.hide_empty)),
+            "display.pager" => self.pager.as_ref().map(|pager| Value::String(pager.clone())),
+            "workweek" => Some(Value::Array(self.workweek.iter()
+                                            .map(|day| Value::String(weekday_name(*day).to_string()))
+                                            .collect())),
+            "calendar.holidays" => Some(Value::Array(self.holidays.iter()
+                                                     .map(|holiday| Value::String(format_holiday(holiday)))
+                                                     .collect())),
+            "calendar.ics" => self.holiday_file.as_ref().map(|path| Value::String(path.to_string_lossy().into_owned())),
+            "calendar.pto" => Some(Value::Array(self.pto.iter()
+                                               .map(|range| Value::String(format_range(range)))
+                                               .collect())),
+            "day.timezone" => self.clock.zone.map(|zone| Value::String(zone.name().to_string())),
+            "day.rollover" => Some(Value::String(self.clock.rollover.format("%H:%M").to_string())),
+            "carry_over" => Some(Value::String(self.carry_over.name().to_string())),
+            "team.identity" => self.identity.as_ref().map(|identity| Value::String(identity.clone())),
+            "team.members" => Some(Value::Array(self.members.iter().map(|m| Value::String(m.clone())).collect())),
+            "team.deadline" => self.deadline.map(|time| Value::String(time.format("%H:%M").to_string())),
+            "post.webhook" => self.webhook.as_ref().map(|url| Value::String(url.clone())),
+            "post.chat" => Some(Value::String(self.chat.name().to_string())),
+            "email.from" => self.email_from.as_ref().map(|from| Value::String(from.clone())),
+            "email.to" => Some(Value::Array(self.email_to.iter().map(|to| Value::String(to.clone())).collect())),
+            "email.server" => self.smtp_server.as_ref().map(|server| Value::String(server.clone())),
+            "email.security" => Some(Value::String(self.smtp_security.name().to_string())),
+            "email.username" => self.smtp_username.as_ref().map(|username| Value::String(username.clone())),
+            "git.repos" => Some(Value::Array(self.git_repos.iter()
+                                             .map(|repo| Value::String(repo.to_string_lossy().into_owned()))
+                                             .collect())),
+            "git.author" => self.git_author.as_ref().map(|author| Value::String(author.clone())),
+            "sync.remote" => Some(Value::String(self.sync_remote.clone())),
+            _ => None,
+        }
+    }
+}
+
+fn format_holiday(holiday: &Holiday) -> String {
+    format!("{} {}", holiday.date.format("%F"), holiday.name).trim_end().to_string()
+}
+
+/// Writes time off the way `calendar.pto` takes it.
+pub fn format_range(&(from, to): &(NaiveDate, NaiveDate)) -> String {
+    if from == to {
+        from.format("%F").to_string()
+    } else {
+        format!("{}..{}", from.format("%F"), to.format("%F"))
+    }
+}
+
+/// Profile names end up in file names and config keys, so they are kept
+/// simple.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use config::CarryOver;
+    use chatify::Chat;
+    use standup::Aspect;
+    use chrono::NaiveTime;
+    use toml::Value;
+
+    fn table(toml: &str) -> Table {
+        toml.parse::<Value>().unwrap().as_table().unwrap().clone()
+    }
+
+    #[test]
+    fn it_will_inherit_everything_but_the_data_path() {
+        let default = Profile::from_table("default", &table("carry_over = \"all\"\n[display]\norder = [\"today\"]"),
+                                          None, "").unwrap();
+        let guild = Profile::from_table("guild", &table(""), Some(&default), "profiles.guild.").unwrap();
+        assert_eq!(guild.layout.order, vec![Aspect::Today]);
+        assert_eq!(guild.carry_over, CarryOver::All);
+        assert!(guild.data_path.ends_with(".standup.guild.json"));
+        assert!(default.data_path.ends_with(".standup.json"));
+    }
+
+    #[test]
+    fn it_can_read_the_team_settings() {
+        let profile = Profile::from_table("default", &table(
+            "[team]\nidentity = \"alice\"\nmembers = [\"alice\", \"bob\"]\ndeadline = \"09:30\""), None, "").unwrap();
+        assert_eq!(profile.identity, Some("alice".to_string()));
+        assert_eq!(profile.members, vec!["alice", "bob"]);
+        assert_eq!(profile.deadline, Some(NaiveTime::from_hms_opt(9, 30, 0).unwrap()));
+        assert!(Profile::from_table("default", &table("[team]\ndeadline = \"soon\""), None, "").is_err());
+    }
+
+    #[test]
+    fn it_can_read_the_day_settings() {
+        let default = Profile::from_table("default", &table(
+            "[day]\ntimezone = \"Europe/Berlin\"\nrollover = \"04:00\""), None, "").unwrap();
+        assert_eq!(default.clock.rollover, NaiveTime::from_hms_opt(4, 0, 0).unwrap());
+        let guild = Profile::from_table("guild", &table(""), Some(&default), "").unwrap();
+        assert_eq!(guild.get("day.timezone"), Some(Value::String("Europe/Berlin".to_string())));
+        assert!(Profile::from_table("default", &table("[day]\ntimezone = \"Berlin\""), None, "").is_err());
+        assert!(Profile::from_table("default", &table("[day]\nrollover = \"4am\""), None, "").is_err());
+    }
+
+    #[test]
+    fn it_can_read_the_post_settings() {
+        let default = Profile::from_table("default", &table("[post]\nwebhook = \"https://hooks.example.com/1\""),
+                                          None, "").unwrap();
+        assert_eq!(default.chat, Chat::Slack);
+        let guild = Profile::from_table("guild", &table("[post]\nchat = \"teams\""), Some(&default), "").unwrap();
+        assert_eq!(guild.webhook, Some("https://hooks.example.com/1".to_string()));
+        assert_eq!(guild.chat, Chat::Teams);
+        assert!(Profile::from_table("default", &table("[post]\nchat = \"irc\""), None, "").is_err());
+    }
+
+    #[test]
+    fn it_will_only_allow_simple_names() {
+        assert!(is_valid_name("guild-2"));
+        assert!(!is_valid_name("my guild"));
+        assert!(!is_valid_name("a.b"));
+        assert!(!is_valid_name(""));
+    }
+}
@@ -0,0 +1,158 @@
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use argon2::Argon2;
+use chacha20poly1305::{XChaCha20Poly1305, XNonce, Key, KeyInit};
+use chacha20poly1305::aead::{Aead, AeadCore, OsRng, Payload};
+use chacha20poly1305::aead::rand_core::RngCore;
+use rpassword;
+use ::CliError;
+use ::StandupError;
+
+/// Every encrypted file starts with this, which is also authenticated along
+/// with the contents.
+const MAGIC: &'static [u8] = b"standup-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+///
+/// ## Secret
+///
+/// The passphrase or key file contents the encryption key is derived from.
+/// A fresh salt is used every time the file is written, so the key itself is
+/// derived on each encrypt and decrypt rather than kept around.
+///
+#[derive(Clone)]
+pub struct Secret {
+    bytes: Vec<u8>,
+}
+
+impl Secret {
+    pub fn new(bytes: Vec<u8>) -> Secret {
+        Secret { bytes: bytes }
+    }
+
+    /// Whether `obtain` can get the secret without asking for it.
+    pub fn in_env() -> bool {
+        env::var_os("STANDUP_KEYFILE").is_some() || env::var("STANDUP_PASSPHRASE").is_ok()
+    }
+
+    /// Reads the key file named by `STANDUP_KEYFILE`, or the passphrase in
+    /// `STANDUP_PASSPHRASE`, and otherwise asks for a passphrase on the
+    /// terminal. When `confirm` is set the passphrase has to be typed twice.
+    pub fn obtain(confirm: bool) -> Result<Secret, CliError> {
+        if let Some(path) = env::var_os("STANDUP_KEYFILE") {
+            let mut bytes = vec![];
+            try!(File::open(path).and_then(|mut file| file.read_to_end(&mut bytes)).map_err(CliError::Io));
+            return Ok(Secret::new(bytes));
+        }
+        if let Ok(passphrase) = env::var("STANDUP_PASSPHRASE") {
+            return Ok(Secret::new(passphrase.into_bytes()));
+        }
+        let passphrase = try!(rpassword::prompt_password("Passphrase: ").map_err(CliError::Io));
+        if confirm {
+            let again = try!(rpassword::prompt_password("Confirm passphrase: ").map_err(CliError::Io));
+            if again != passphrase {
+                return Err(CliError::Cli(StandupError::PassphraseMismatch));
+            }
+        }
+        Ok(Secret::new(passphrase.into_bytes()))
+    }
+
+    fn derive(&self, salt: &[u8]) -> Result<Key, CliError> {
+        let mut key = Key::default();
+        try!(Argon2::default()
+             .hash_password_into(&self.bytes, salt, &mut key)
+             .map_err(|_| CliError::Cli(StandupError::EmptySecret)));
+        Ok(key)
+    }
+}
+
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Lays the file out as the magic header, the salt, the nonce and then the
+/// ciphertext.
+pub fn encrypt(secret: &Secret, plaintext: &[u8]) -> Result<Vec<u8>, CliError> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let key = try!(secret.derive(&salt));
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = try!(XChaCha20Poly1305::new(&key)
+        .encrypt(&nonce, Payload { msg: plaintext, aad: MAGIC })
+        .map_err(|_| CliError::Cli(StandupError::EncryptionFailed)));
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + SALT_LEN + NONCE_LEN + ciphertext.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&salt);
+    bytes.extend_from_slice(&nonce);
+    bytes.extend_from_slice(&ciphertext);
+    Ok(bytes)
+}
+
+/// Fails with `WrongSecret` when the passphrase or key file is not the one
+/// the file was written with, or when the file has been tampered with.
+pub fn decrypt(secret: &Secret, bytes: &[u8]) -> Result<Vec<u8>, CliError> {
+    if !is_encrypted(bytes) || bytes.len() < MAGIC.len() + SALT_LEN + NONCE_LEN {
+        return Err(CliError::Cli(StandupError::NotEncrypted));
+    }
+    let (salt, rest) = bytes[MAGIC.len()..].split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let key = try!(secret.derive(salt));
+    XChaCha20Poly1305::new(&key)
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: MAGIC })
+        .map_err(|_| CliError::Cli(StandupError::WrongSecret))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::CliError;
+    use ::StandupError;
+
+    #[test]
+    fn it_can_round_trip() {
+        let secret = Secret::new(b"correct horse battery staple".to_vec());
+        let bytes = encrypt(&secret, b"[{\"date\":\"2015-01-01\"}]").unwrap();
+        assert!(is_encrypted(&bytes));
+        assert_eq!(decrypt(&secret, &bytes).unwrap(), b"[{\"date\":\"2015-01-01\"}]".to_vec());
+    }
+
+    #[test]
+    fn it_will_not_leak_the_plaintext() {
+        let secret = Secret::new(b"passphrase".to_vec());
+        let bytes = encrypt(&secret, b"unreleased customer").unwrap();
+        assert!(!bytes.windows(8).any(|window| window == b"customer"));
+    }
+
+    #[test]
+    fn it_will_use_a_fresh_salt_and_nonce_each_time() {
+        let secret = Secret::new(b"passphrase".to_vec());
+        assert!(encrypt(&secret, b"same").unwrap() != encrypt(&secret, b"same").unwrap());
+    }
+
+    #[test]
+    fn it_will_reject_the_wrong_passphrase() {
+        let bytes = encrypt(&Secret::new(b"right".to_vec()), b"secret").unwrap();
+        match decrypt(&Secret::new(b"wrong".to_vec()), &bytes) {
+            Err(CliError::Cli(StandupError::WrongSecret)) => {},
+            other => panic!("expected WrongSecret, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_will_reject_tampered_files() {
+        let secret = Secret::new(b"passphrase".to_vec());
+        let mut bytes = encrypt(&secret, b"secret").unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        assert!(decrypt(&secret, &bytes).is_err());
+    }
+
+    #[test]
+    fn it_will_refuse_plaintext() {
+        let secret = Secret::new(b"passphrase".to_vec());
+        assert!(decrypt(&secret, b"[]").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use standup::{Standup, Aspect};
+use csv::Writer;
+use csv::Result;
+
+static HEADERS: &'static [&'static str] = &["date", "aspect", "position", "text"];
+
+/// Writes one row per entry. Positions are 1-based, matching what `show` prints.
+pub fn serialize(standups: &[&Standup]) -> Result<String> {
+    let mut writer = Writer::from_writer(vec![]);
+    try!(writer.write_record(HEADERS));
+    for standup in standups {
+        let date = standup.date.format("%F").to_string();
+        for aspect in Aspect::all() {
+            for (i, message) in standup.messages(aspect).iter().enumerate() {
+                let position = (i + 1).to_string();
+                try!(writer.write_record(&[date.as_str(), aspect.name(), position.as_str(), message.as_str()]));
+            }
+        }
+    }
+    let bytes = try!(writer.into_inner().map_err(|err| err.into_error()));
+    Ok(String::from_utf8(bytes).unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::*;
+    use csv::{Reader, StringRecord};
+    use std::collections::BTreeMap;
+    use standup::{Standup, Aspect};
+
+    /// Rebuilds standups from rows. Rows with an unknown aspect or an invalid
+    /// date are skipped, and entries are ordered by their position column.
+    fn deserialize(csv: String) -> Result<Vec<Standup>> {
+        let mut rows: BTreeMap<NaiveDate, Vec<(Aspect, usize, String)>> = BTreeMap::new();
+        let mut reader = Reader::from_reader(csv.as_bytes());
+        for record in reader.records() {
+            let record = try!(record);
+            if let Some((date, row)) = parse_row(&record) {
+                rows.entry(date).or_insert(vec![]).push(row);
+            }
+        }
+        Ok(rows.into_iter().map(|(date, mut entries)| {
+            entries.sort_by_key(|&(_, position, _)| position);
+            let standup = Standup::from_date(date);
+            entries.iter().fold(standup, |s, &(aspect, _, ref text)| s.add(aspect, text))
+        }).collect())
+    }
+
+    fn parse_row(record: &StringRecord) -> Option<(NaiveDate, (Aspect, usize, String))> {
+        let date = record.get(0).and_then(|d| NaiveDate::parse_from_str(d, "%F").ok());
+        let aspect = record.get(1).and_then(Aspect::from_name);
+        let position = record.get(2).and_then(|p| p.parse::<usize>().ok());
+        let text = record.get(3).map(|t| t.to_string());
+        match (date, aspect, position, text) {
+            (Some(date), Some(aspect), Some(position), Some(text)) => Some((date, (aspect, position, text))),
+            _ => None
+        }
+    }
+
+    #[test]
+    fn it_will_write_a_header() {
+        let csv = serialize(&[]).unwrap();
+        assert_eq!(csv, "date,aspect,position,text\n");
+    }
+
+    #[test]
+    fn it_will_write_a_row_per_entry() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "first")
+            .add(Aspect::Today, "second")
+            .add(Aspect::Blocker, "blocked");
+        let csv = serialize(&[&standup]).unwrap();
+        assert!(csv.contains("2015-03-23,today,1,first\n"));
+        assert!(csv.contains("2015-03-23,today,2,second\n"));
+        assert!(csv.contains("2015-03-23,blocker,1,blocked\n"));
+    }
+
+    #[test]
+    fn it_will_quote_commas_and_quotes() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "say \"hi\", then leave");
+        let csv = serialize(&[&standup]).unwrap();
+        assert!(csv.contains("\"say \"\"hi\"\", then leave\""));
+    }
+
+    #[test]
+    fn it_will_round_trip() {
+        let first = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "today, with a comma")
+            .add(Aspect::Today, "and a\nnewline")
+            .add(Aspect::Yesterday, "yesterday");
+        let second = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 24).unwrap())
+            .add(Aspect::Blocker, "blocker");
+        let standups = deserialize(serialize(&[&first, &second]).unwrap()).unwrap();
+        assert_eq!(standups, vec![first, second]);
+    }
+
+    #[test]
+    fn it_will_order_entries_by_position() {
+        let csv = "date,aspect,position,text\n\
+                   2015-03-23,today,2,second\n\
+                   2015-03-23,today,1,first\n".to_string();
+        let standups = deserialize(csv).unwrap();
+        assert_eq!(standups[0].today, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn it_will_skip_rows_it_does_not_understand() {
+        let csv = "date,aspect,position,text\n\
+                   2015-03-23,tomorrow,1,nope\n\
+                   not-a-date,today,1,nope\n\
+                   2015-03-23,today,1,yes\n".to_string();
+        let standups = deserialize(csv).unwrap();
+        assert_eq!(standups.len(), 1);
+        assert_eq!(standups[0].today, vec!["yes"]);
+    }
+}
@@ -0,0 +1,231 @@
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use lettre::{Message, SmtpTransport, Transport};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use rpassword;
+use ::CliError;
+use ::StandupError;
+use standup::{Standup, Aspect, Layout};
+use publish::escape;
+
+/// How the connection to the SMTP server is protected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Security {
+    /// Connects in plain text and upgrades with STARTTLS, usually on 587.
+    StartTls,
+    /// Connects over TLS from the start, usually on 465.
+    Tls,
+    /// Never encrypts. Only meant for a relay on the same machine.
+    Off,
+}
+
+impl Security {
+    pub fn from_name(name: &str) -> Option<Security> {
+        match name {
+            "starttls" => Some(Security::StartTls),
+            "tls" => Some(Security::Tls),
+            "none" => Some(Security::Off),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Security::StartTls => "starttls",
+            Security::Tls => "tls",
+            Security::Off => "none",
+        }
+    }
+}
+
+///
+/// ## Smtp
+///
+/// An SMTP server to send through, given as `host:port`. The port defaults to
+/// the usual one for the security.
+///
+pub struct Smtp {
+    pub server: String,
+    pub security: Security,
+    pub username: Option<String>,
+}
+
+impl Smtp {
+    pub fn send(&self, message: &Message) -> Result<(), CliError> {
+        let (host, port) = match self.server.rfind(':') {
+            Some(at) => {
+                let port = try!(self.server[at + 1..].parse::<u16>().map_err(|_| CliError::Cli(
+                    StandupError::InvalidConfig("email.server".to_string(), "must look like host:port".to_string()))));
+                (&self.server[..at], port)
+            },
+            None => (self.server.as_str(), if self.security == Security::Tls { 465 } else { 587 }),
+        };
+        let builder = try!(match self.security {
+            Security::StartTls => SmtpTransport::starttls_relay(host),
+            Security::Tls => SmtpTransport::relay(host),
+            Security::Off => Ok(SmtpTransport::builder_dangerous(host)),
+        }.map_err(|err| CliError::Smtp(err.to_string())));
+        let mut builder = builder.port(port).timeout(Some(Duration::from_secs(30)));
+        if let Some(ref username) = self.username {
+            builder = builder.credentials(Credentials::new(username.clone(), try!(password())));
+        }
+        builder.build().send(message)
+            .map(|_| ())
+            .map_err(|err| CliError::Smtp(err.to_string()))
+    }
+}
+
+/// Reads the SMTP password from `STANDUP_SMTP_PASSWORD`, or asks for it.
+fn password() -> Result<String, CliError> {
+    if let Ok(password) = env::var("STANDUP_SMTP_PASSWORD") {
+        return Ok(password);
+    }
+    rpassword::prompt_password("SMTP password: ").map_err(CliError::Io)
+}
+
+/// Builds an email with the days' standups as both plain text and HTML.
+pub fn message(from: &str, to: &[String], subject: &str, days: &[Vec<&Standup>], layout: &Layout)
+               -> Result<Message, CliError> {
+    let mut builder = Message::builder()
+        .from(try!(mailbox(from)))
+        .subject(subject);
+    for address in to {
+        builder = builder.to(try!(mailbox(address)));
+    }
+    let text = days.iter().map(|day| layout.render_day(day)).collect::<Vec<String>>().join("\n");
+    let html = render_html(subject, days, layout);
+    builder.multipart(MultiPart::alternative_plain_html(text, html))
+        .map_err(|err| CliError::Smtp(err.to_string()))
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, CliError> {
+    address.parse().map_err(|_| CliError::Cli(StandupError::InvalidAddress(address.to_string())))
+}
+
+/// Writes the email as an `.eml` file that any mail client can open.
+pub fn write_eml(message: &Message, path: &Path) -> Result<(), CliError> {
+    File::create(path)
+        .and_then(|mut file| file.write_all(&message.formatted()))
+        .map_err(CliError::Io)
+}
+
+/// Mail clients ignore style sheets, so blockers are highlighted inline.
+fn render_html(subject: &str, days: &[Vec<&Standup>], layout: &Layout) -> String {
+    let mut out = format!("<!DOCTYPE html>\n<html>\n<body style=\"font-family: sans-serif\">\n<h1>{}</h1>\n",
+                          escape(subject));
+    for day in days {
+        out.push_str(&format!("<h2>{}</h2>\n", escape(&day[0].date.format(&layout.date_format).to_string())));
+        for standup in day {
+            if let Some(ref author) = standup.author {
+                out.push_str(&format!("<h3>{}</h3>\n", escape(author)));
+            }
+            for &aspect in layout.order.iter() {
+                let messages = standup.messages(aspect);
+                if messages.is_empty() && layout.hide_empty.contains(&aspect) {
+                    continue;
+                }
+                let style = if aspect == Aspect::Blocker { " style=\"color: #d73a49\"" } else { "" };
+                out.push_str(&format!("<h4{}>{}</h4>\n<ul>\n", style, aspect));
+                for message in messages.iter() {
+                    out.push_str(&format!("<li{}>{}</li>\n", style, escape(message)));
+                }
+                out.push_str("</ul>\n");
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+    use chrono::*;
+    use standup::{Standup, Aspect, Layout};
+
+    /// Accepts a single delivery on an ephemeral port, advertising AUTH so
+    /// that credentials are sent, and hands back the whole conversation.
+    fn fake_smtp() -> (String, Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let (sender, received) = channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut conversation = String::new();
+            writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+            let mut in_data = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                conversation.push_str(&line);
+                let reply: &[u8] = if in_data {
+                    if line == ".\r\n" { in_data = false; b"250 queued\r\n" } else { b"" }
+                } else if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 ok\r\n"
+                } else if line.starts_with("DATA") {
+                    in_data = true;
+                    b"354 go ahead\r\n"
+                } else if line.starts_with("QUIT") {
+                    writer.write_all(b"221 bye\r\n").unwrap();
+                    break;
+                } else {
+                    b"250 ok\r\n"
+                };
+                writer.write_all(reply).unwrap();
+                line.clear();
+            }
+            sender.send(conversation).unwrap();
+        });
+        (server, received)
+    }
+
+    fn message() -> Message {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())
+            .add(Aspect::Today, "review")
+            .add(Aspect::Blocker, "waiting on <ops>");
+        let days = vec![vec![&standup]];
+        super::message("Standup <standup@example.com>", &["boss@example.com".to_string()],
+                       "Standup 2016-03-07", &days, &Layout::default()).unwrap()
+    }
+
+    #[test]
+    fn it_will_send_text_and_html() {
+        let text = String::from_utf8(message().formatted()).unwrap();
+        assert!(text.contains("Subject: Standup 2016-03-07"));
+        assert!(text.contains("multipart/alternative"));
+        assert!(text.contains("Content-Type: text/plain"));
+        assert!(text.contains("Content-Type: text/html"));
+        assert!(text.contains("waiting on &lt;ops&gt;"));
+    }
+
+    #[test]
+    fn it_will_reject_bad_addresses() {
+        let days: Vec<Vec<&Standup>> = vec![];
+        assert!(super::message("nobody", &[], "Standup", &days, &Layout::default()).is_err());
+    }
+
+    #[test]
+    fn it_can_deliver_through_smtp() {
+        env::set_var("STANDUP_SMTP_PASSWORD", "hunter2");
+        let (server, received) = fake_smtp();
+        let smtp = Smtp { server: server, security: Security::Off, username: Some("me".to_string()) };
+        smtp.send(&message()).unwrap();
+        let conversation = received.recv().unwrap();
+        assert!(conversation.contains("AUTH PLAIN"));
+        assert!(conversation.contains("MAIL FROM:<standup@example.com>"));
+        assert!(conversation.contains("RCPT TO:<boss@example.com>"));
+        assert!(conversation.contains("Subject: Standup 2016-03-07"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::Path;
+use chrono::*;
+use ::CliError;
+use store::git;
+
+/// A commit that could go into the standup.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Commit {
+    pub repo: String,
+    pub hash: String,
+    pub summary: String,
+}
+
+impl Commit {
+    /// The commit as a standup entry, such as `Fix the build (standup 1a2b3c4)`.
+    pub fn entry(&self) -> String {
+        format!("{} ({} {})", self.summary, self.repo, self.hash)
+    }
+}
+
+/// The commits the author made in the repository between the two times,
+/// oldest first. Every branch is searched, and merges are left out.
+pub fn commits(repo: &Path, author: &str, since: DateTime<Utc>, until: DateTime<Utc>)
+               -> Result<Vec<Commit>, CliError> {
+    let name = repo.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let since = format!("--since={}", since.format("%Y-%m-%d %H:%M:%S %z"));
+    let until = format!("--until={}", until.format("%Y-%m-%d %H:%M:%S %z"));
+    let author = format!("--author=<{}>", author);
+    let log = try!(git::run(repo, &["log", "--all", "--no-merges", "--reverse", "--regexp-ignore-case",
+                                    "--fixed-strings", &author, &since, &until, "--format=%h%x09%s"]));
+    Ok(log.lines().filter_map(|line| {
+        let mut parts = line.splitn(2, '\t');
+        match (parts.next(), parts.next()) {
+            (Some(hash), Some(summary)) => Some(Commit {
+                repo: name.clone(),
+                hash: hash.to_string(),
+                summary: summary.to_string(),
+            }),
+            _ => None,
+        }
+    }).collect())
+}
+
+/// Leaves out commits that are already in the entries, matched by their
+/// hash, along with repeats of the same commit.
+pub fn unrecorded(commits: Vec<Commit>, entries: &[String]) -> Vec<Commit> {
+    let mut seen: Vec<String> = vec![];
+    commits.into_iter().filter(|commit| {
+        let recorded = entries.iter().any(|entry| entry.split_whitespace()
+                                          .any(|word| word.trim_matches(|c| c == '(' || c == ')') == commit.hash));
+        if recorded || seen.contains(&commit.hash) {
+            return false;
+        }
+        seen.push(commit.hash.clone());
+        true
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::process::Command;
+    use std::path::Path;
+    use store::test::scratch;
+    use clock::Clock;
+
+    fn commit(repo: &Path, email: &str, date: &str, summary: &str) {
+        let status = Command::new("git")
+            .current_dir(repo)
+            .args(&["-c", "user.name=someone", "-c", &format!("user.email={}", email),
+                    "commit", "--quiet", "--allow-empty", "-m", summary])
+            .env("GIT_AUTHOR_DATE", date)
+            .env("GIT_COMMITTER_DATE", date)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    #[test]
+    fn it_will_find_the_authors_commits_in_the_window() {
+        let repo = scratch("gitlog").join("widgets");
+        git::init(&repo, None, "origin").unwrap();
+        commit(&repo, "me@example.com", "2016-03-03T17:00:00", "Too early");
+        commit(&repo, "me@example.com", "2016-03-04T10:00:00", "Fix the build");
+        commit(&repo, "you@example.com", "2016-03-04T11:00:00", "Not mine");
+        commit(&repo, "Me@Example.com", "2016-03-05T12:00:00", "Weekend work");
+        commit(&repo, "me@example.com", "2016-03-07T09:00:00", "Too late");
+
+        let found = commits(&repo, "me@example.com",
+                            Clock::system().start_of(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap()),
+                            Clock::system().start_of(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())).unwrap();
+        let summaries: Vec<&str> = found.iter().map(|commit| commit.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Fix the build", "Weekend work"]);
+        assert!(found[0].entry().starts_with("Fix the build (widgets "));
+    }
+
+    #[test]
+    fn it_will_skip_commits_already_recorded() {
+        let commit = |hash: &str| Commit { repo: "widgets".to_string(), hash: hash.to_string(), summary: "Fix".to_string() };
+        let entries = vec!["Fix (widgets 1a2b3c4)".to_string()];
+        let left = unrecorded(vec![commit("1a2b3c4"), commit("5d6e7f8"), commit("5d6e7f8")], &entries);
+        assert_eq!(left, vec![commit("5d6e7f8")]);
+    }
+}
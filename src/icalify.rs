@@ -0,0 +1,128 @@
+use standup::Standup;
+
+/// Renders each standup as an all-day VEVENT whose description is the same
+/// text `show` prints.
+pub fn serialize(standups: &[&Standup]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//standup//standup//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for standup in standups {
+        let day = standup.date.format("%Y%m%d").to_string();
+        let (uid, who) = match standup.author {
+            Some(ref author) => (format!("{}-{}", standup.date.format("%F"), author), format!(" ({})", author)),
+            None => (standup.date.format("%F").to_string(), String::new()),
+        };
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@standup", uid));
+        lines.push(format!("DTSTAMP:{}T000000Z", day));
+        lines.push(format!("DTSTART;VALUE=DATE:{}", day));
+        lines.push(format!("DTEND;VALUE=DATE:{}", standup.date.succ_opt().unwrap_or(standup.date).format("%Y%m%d")));
+        lines.push(format!("SUMMARY:{}", escape(&format!("Standup {}{}", standup.date.format("%F"), who))));
+        lines.push(format!("DESCRIPTION:{}", escape(&format!("{}", standup))));
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect::<Vec<String>>().join("")
+}
+
+fn escape(text: &str) -> String {
+    text.trim_end()
+        .replace("\\", "\\\\")
+        .replace(";", "\\;")
+        .replace(",", "\\,")
+        .replace("\n", "\\n")
+}
+
+/// Content lines may not be longer than 75 octets, so longer lines are split
+/// with a CRLF followed by a single space. Splits never land inside a
+/// multi-byte character.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use standup::{Standup, Aspect};
+    use chrono::*;
+
+    fn unfold(ics: &str) -> String {
+        ics.replace("\r\n ", "")
+    }
+
+    fn unescape(text: &str) -> String {
+        text.replace("\\n", "\n")
+            .replace("\\,", ",")
+            .replace("\\;", ";")
+            .replace("\\\\", "\\")
+    }
+
+    #[test]
+    fn it_will_wrap_events_in_a_calendar() {
+        let ics = serialize(&[]);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn it_will_create_an_all_day_event() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 12, 31).unwrap());
+        let ics = serialize(&[&standup]);
+        assert!(ics.contains("DTSTART;VALUE=DATE:20151231\r\n"));
+        assert!(ics.contains("DTEND;VALUE=DATE:20160101\r\n"));
+        assert!(ics.contains("UID:2015-12-31@standup\r\n"));
+    }
+
+    #[test]
+    fn it_will_give_each_person_their_own_event() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 12, 31).unwrap()).set_author(Some("alice".to_string()));
+        let ics = serialize(&[&standup]);
+        assert!(ics.contains("UID:2015-12-31-alice@standup\r\n"));
+        assert!(ics.contains("SUMMARY:Standup 2015-12-31 (alice)\r\n"));
+    }
+
+    #[test]
+    fn it_will_escape_the_description() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "a, b; c");
+        let ics = serialize(&[&standup]);
+        assert!(ics.contains("a\\, b\\; c"));
+    }
+
+    #[test]
+    fn it_will_fold_long_lines() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, &"é".repeat(100));
+        let ics = serialize(&[&standup]);
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= 75);
+        }
+    }
+
+    #[test]
+    fn it_will_round_trip_the_rendered_description() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "today, with a comma")
+            .add(Aspect::Blocker, &"a long blocker ".repeat(10));
+        let ics = unfold(&serialize(&[&standup]));
+        let description = ics.split("\r\n")
+            .find(|line| line.starts_with("DESCRIPTION:"))
+            .map(|line| unescape(&line["DESCRIPTION:".len()..]))
+            .unwrap();
+        assert_eq!(description, format!("{}", standup).trim_end());
+    }
+}
@@ -1,27 +1,171 @@
-use standup::{Standup, Aspect};
+use standup::{Standup, Aspect, Change};
 use chrono::*;
 use serde_json::builder::ObjectBuilder;
-use serde_json::{from_str,from_value,Value,Map};
+use serde_json::{from_str,from_value,to_string_pretty,Value,Map};
 use serde_json::error::Result;
 
 type Obj = Map<String, Value>;
 
-pub fn serialize(standups: &[&Standup]) -> String {
-    let array = standups.iter().map(|standup| {
-        ObjectBuilder::new()
-            .insert("date", standup.date.format("%F").to_string())
-            .insert("today", standup.today.clone())
-            .insert("yesterday", standup.yesterday.clone())
-            .insert("blocker", standup.blocker.clone())
-            .unwrap()
-    });
-    format!("{}", Value::Array(array.collect::<Vec<Value>>()))
+/// How the standups are laid out in the file. Keys are always written in
+/// sorted order, so the same data always produces the same output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Style {
+    /// The whole history as an array on a single line.
+    Compact,
+    /// An indented array with one message per line.
+    Pretty,
+    /// Newline-delimited JSON with one standup per line.
+    Lines,
 }
 
+impl Style {
+    pub fn from_name(name: &str) -> Option<Style> {
+        match name {
+            "compact" => Some(Style::Compact),
+            "pretty" => Some(Style::Pretty),
+            "lines" => Some(Style::Lines),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Style::Compact => "compact",
+            Style::Pretty => "pretty",
+            Style::Lines => "lines",
+        }
+    }
+
+    /// Works out which style some existing JSON was written in.
+    pub fn detect(json: &str) -> Style {
+        let trimmed = json.trim();
+        if !trimmed.starts_with("[") {
+            Style::Lines
+        } else if trimmed.contains("\n") {
+            Style::Pretty
+        } else {
+            Style::Compact
+        }
+    }
+}
+
+pub fn serialize_with(standups: &[&Standup], style: Style) -> String {
+    let values = standups.iter().map(|standup| build_object(standup));
+    match style {
+        Style::Compact => format!("{}", Value::Array(values.collect::<Vec<Value>>())),
+        Style::Pretty => to_string_pretty(&Value::Array(values.collect::<Vec<Value>>())).unwrap() + "\n",
+        Style::Lines => values.map(|value| format!("{}\n", value)).collect(),
+    }
+}
+
+/// Reads standups written in any of the styles.
 pub fn deserialize(json: String) -> Result<Vec<Standup>> {
+    match Style::detect(&json) {
+        Style::Lines => {
+            let mut standups = vec![];
+            for line in json.lines().filter(|line| !line.trim().is_empty()) {
+                standups.push(try!(deserialize_day(line.to_string())));
+            }
+            Ok(standups)
+        },
+        _ => {
+            from_str(&json)
+                .and_then(|parsed| from_value::<Vec<Obj>>(parsed))
+                .map(|objs| objs.iter().map(|obj| build_standup(obj)).collect())
+        }
+    }
+}
+
+/// Writes a single standup as an indented object, one message per line, so
+/// that a file per day diffs and merges cleanly.
+pub fn serialize_day(standup: &Standup) -> String {
+    to_string_pretty(&build_object(standup)).unwrap() + "\n"
+}
+
+pub fn deserialize_day(json: String) -> Result<Standup> {
     from_str(&json)
-        .and_then(|parsed| from_value::<Vec<Obj>>(parsed))
-        .map(|objs| objs.iter().map(|obj| build_standup(obj)).collect())
+        .and_then(|parsed| from_value::<Obj>(parsed))
+        .map(|obj| build_standup(&obj))
+}
+
+/// What `show --output json` prints: the day, everyone's standups on it and
+/// who had not posted by the deadline.
+pub fn serialize_show(date: &NaiveDate, standups: &[&Standup], missing: &[String]) -> String {
+    let value = ObjectBuilder::new()
+        .insert("date", date.format("%F").to_string())
+        .insert("standups", standups.iter().map(|standup| build_object(standup)).collect::<Vec<Value>>())
+        .insert("missing", missing.to_vec())
+        .unwrap();
+    to_string_pretty(&value).unwrap() + "\n"
+}
+
+/// What a failure prints with `--output json`. The kind names the error for
+/// scripts to match on, while the message is meant for people.
+pub fn serialize_error(message: &str, kind: &str) -> String {
+    let value = ObjectBuilder::new()
+        .insert("error", message)
+        .insert("kind", kind)
+        .unwrap();
+    to_string_pretty(&value).unwrap() + "\n"
+}
+
+/// Writes a change as a single journal line, along with when it was made and
+/// a description of it so the journal doubles as an audit log.
+pub fn serialize_change(change: &Change, message: &str, at: &str) -> String {
+    let builder = ObjectBuilder::new()
+        .insert("at", at)
+        .insert("message", message);
+    let value = match *change {
+        Change::Put(ref standup) => builder.insert("op", "put").insert("standup", build_object(standup)),
+        Change::Delete(ref date, ref author) => {
+            let builder = builder.insert("op", "delete").insert("date", date.format("%F").to_string());
+            match *author {
+                Some(ref author) => builder.insert("author", author.clone()),
+                None => builder,
+            }
+        },
+    };
+    format!("{}", value.unwrap())
+}
+
+/// Reads a journal line back. Lines with an operation this version does not
+/// know about come back as `None`.
+pub fn deserialize_change(line: &str) -> Result<Option<Change>> {
+    from_str(line)
+        .and_then(|parsed| from_value::<Obj>(parsed))
+        .map(|obj| {
+            match obj.get("op").and_then(|op| op.as_string()) {
+                Some("put") => {
+                    obj.get("standup")
+                        .and_then(|standup| standup.as_object())
+                        .map(|standup| Change::Put(build_standup(standup)))
+                },
+                Some("delete") => {
+                    obj.get("date")
+                        .and_then(|date| date.as_string())
+                        .and_then(|date| NaiveDate::parse_from_str(date, "%F").ok())
+                        .map(|date| {
+                            let author = obj.get("author").and_then(|a| a.as_string()).map(|a| a.to_string());
+                            Change::Delete(date, author)
+                        })
+                },
+                _ => None
+            }
+        })
+}
+
+/// The author is only written for standups that have one, so a log kept by
+/// one person looks the same as it always has.
+pub fn build_object(standup: &Standup) -> Value {
+    let builder = ObjectBuilder::new()
+        .insert("date", standup.date.format("%F").to_string())
+        .insert("today", standup.today.clone())
+        .insert("yesterday", standup.yesterday.clone())
+        .insert("blocker", standup.blocker.clone());
+    match standup.author {
+        Some(ref author) => builder.insert("author", author.clone()).unwrap(),
+        None => builder.unwrap(),
+    }
 }
 
 fn build_standup(obj: &Obj) -> Standup {
@@ -30,7 +174,7 @@ fn build_standup(obj: &Obj) -> Standup {
     let s = add_message(s, &obj, "yesterday",   |s, msg| s.add(Aspect::Yesterday, msg));
     let s = add_message(s, &obj, "blocker",     |s, msg| s.add(Aspect::Blocker, msg));
     let s = set_date(s, &obj);
-    s
+    s.set_author(obj.get("author").and_then(|author| author.as_string()).map(|author| author.to_string()))
 }
 
 fn add_message<F>(standup: Standup, obj: &Obj, key: &str, op: F) -> Standup
@@ -53,7 +197,7 @@ fn set_date(standup: Standup, obj: &Obj) -> Standup {
         .and_then(|date_value| date_value.as_string())
         .map_or(standup.clone(), |date_string| {
             if let Ok(date) = NaiveDate::parse_from_str(date_string, "%F") {
-                standup.set_date(Local.ymd(date.year(), date.month(), date.day()))
+                standup.set_date(date)
             } else {
                 standup
             }
@@ -63,8 +207,28 @@ fn set_date(standup: Standup, obj: &Obj) -> Standup {
 #[cfg(test)]
 mod test {
     use super::*;
-    use standup::{Standup, Aspect};
-    use chrono::*;
+    use standup::{Standup, Aspect, Change};
+
+    fn serialize(standups: &[&Standup]) -> String {
+        serialize_with(standups, Style::Compact)
+    }
+
+    #[test]
+    fn it_will_describe_a_day_and_errors() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap()).add(Aspect::Today, "review");
+        let json = serialize_show(&NaiveDate::from_ymd_opt(2016, 3, 7).unwrap(), &[&standup], &["bob".to_string()]);
+        assert_eq!(json, concat!("{\n",
+                                 "  \"date\": \"2016-03-07\",\n",
+                                 "  \"missing\": [\n    \"bob\"\n  ],\n",
+                                 "  \"standups\": [\n    {\n",
+                                 "      \"blocker\": [],\n",
+                                 "      \"date\": \"2016-03-07\",\n",
+                                 "      \"today\": [\n        \"review\"\n      ],\n",
+                                 "      \"yesterday\": []\n",
+                                 "    }\n  ]\n}\n"));
+        assert_eq!(serialize_error("no", "invalid_date"),
+                   "{\n  \"error\": \"no\",\n  \"kind\": \"invalid_date\"\n}\n");
+    }
 
     #[test]
     fn it_will_include_todays_notes() {
@@ -96,7 +260,7 @@ mod test {
 
     #[test]
     fn it_will_include_the_date() {
-        let standup = Standup::from_date(Local.ymd(2015, 3, 23));
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap());
         let json = serialize(&[&standup]);
         assert!(json.as_str().contains("date\":\"2015-03-23\""));
     }
@@ -124,9 +288,106 @@ mod test {
 
     #[test]
     fn it_will_load_in_the_date() {
-        let date = Local.ymd(2015, 3, 23);
+        let date = NaiveDate::from_ymd_opt(2015, 3, 23).unwrap();
         let standup = Standup::from_date(date.clone());
         let standups = deserialize(serialize(&[&standup])).unwrap();
         assert_eq!(standups[0].date, date);
     }
+
+    #[test]
+    fn it_will_write_one_message_per_line_for_a_day() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "first")
+            .add(Aspect::Today, "second");
+        let json = serialize_day(&standup);
+        assert!(json.contains("\n    \"first\",\n    \"second\"\n"));
+        assert!(json.ends_with("}\n"));
+    }
+
+    #[test]
+    fn it_will_load_a_single_day() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Blocker, "blocker");
+        assert_eq!(deserialize_day(serialize_day(&standup)).unwrap(), standup);
+    }
+
+    #[test]
+    fn it_will_round_trip_the_author() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap()).set_author(Some("alice".to_string()));
+        let json = serialize(&[&standup]);
+        assert!(json.contains("\"author\":\"alice\""));
+        assert_eq!(deserialize(json).unwrap(), vec![standup]);
+    }
+
+    #[test]
+    fn it_will_write_keys_in_a_stable_order() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap());
+        let json = serialize(&[&standup]);
+        assert_eq!(json, "[{\"blocker\":[],\"date\":\"2015-03-23\",\"today\":[],\"yesterday\":[]}]");
+    }
+
+    #[test]
+    fn it_can_pretty_print() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap()).add(Aspect::Today, "today");
+        let json = serialize_with(&[&standup], Style::Pretty);
+        assert!(json.starts_with("[\n  {\n"));
+        assert!(json.contains("\"today\": [\n      \"today\"\n    ]"));
+    }
+
+    #[test]
+    fn it_can_write_one_standup_per_line() {
+        let first = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap());
+        let second = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 24).unwrap());
+        let json = serialize_with(&[&first, &second], Style::Lines);
+        assert_eq!(json.lines().count(), 2);
+        assert!(json.lines().all(|line| line.starts_with("{") && line.ends_with("}")));
+    }
+
+    #[test]
+    fn it_can_detect_the_style() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap());
+        for style in vec![Style::Compact, Style::Pretty, Style::Lines] {
+            assert_eq!(Style::detect(&serialize_with(&[&standup], style)), style);
+        }
+    }
+
+    #[test]
+    fn it_will_load_every_style() {
+        let first = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap()).add(Aspect::Today, "today");
+        let second = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 24).unwrap()).add(Aspect::Blocker, "blocker");
+        for style in vec![Style::Compact, Style::Pretty, Style::Lines] {
+            let standups = deserialize(serialize_with(&[&first, &second], style)).unwrap();
+            assert_eq!(standups, vec![first.clone(), second.clone()]);
+        }
+    }
+
+    #[test]
+    fn it_will_load_an_empty_file() {
+        assert_eq!(deserialize("".to_string()).unwrap().len(), 0);
+    }
+
+    #[test]
+    fn it_will_write_a_change_on_one_line() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap()).add(Aspect::Today, "a\nb");
+        let line = serialize_change(&Change::Put(standup), "Add today entry", "2015-03-23T09:00:00+00:00");
+        assert!(!line.contains("\n"));
+        assert!(line.contains("\"message\":\"Add today entry\""));
+        assert!(line.contains("\"at\":\"2015-03-23T09:00:00+00:00\""));
+    }
+
+    #[test]
+    fn it_will_round_trip_changes() {
+        let put = Change::Put(Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap()).add(Aspect::Blocker, "blocker"));
+        let delete = Change::Delete(NaiveDate::from_ymd_opt(2015, 3, 24).unwrap(), None);
+        let delete_by = Change::Delete(NaiveDate::from_ymd_opt(2015, 3, 24).unwrap(), Some("alice".to_string()));
+        for change in vec![put, delete, delete_by] {
+            let line = serialize_change(&change, "", "");
+            assert_eq!(deserialize_change(&line).unwrap(), Some(change));
+        }
+    }
+
+    #[test]
+    fn it_will_skip_unknown_changes() {
+        assert_eq!(deserialize_change("{\"op\":\"rename\"}").unwrap(), None);
+    }
 }
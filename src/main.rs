@@ -1,24 +1,78 @@
 extern crate clap;
 extern crate chrono;
+extern crate chrono_tz;
 extern crate serde;
 extern crate serde_json;
+extern crate serde_yaml;
+extern crate csv;
+extern crate argon2;
+extern crate chacha20poly1305;
+extern crate rpassword;
+extern crate toml;
+extern crate tiny_http;
+extern crate ureq;
+extern crate lettre;
+extern crate ratatui;
+extern crate crossterm;
 
-use clap::{Arg, SubCommand, ArgMatches};
+use clap::{Arg, ArgGroup, SubCommand, ArgMatches, AppSettings, Shell};
 use std::io;
+use std::io::{Read, Write, BufRead};
+use std::fmt;
+use std::process;
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::time::Duration;
+use chrono::{Datelike, NaiveDate};
 
 mod standup;
 mod jsonify;
+mod csvify;
+mod yamlify;
+mod icalify;
 mod app;
+mod store;
+mod crypto;
+mod config;
+mod server;
+mod publish;
+mod chatify;
+mod webhook;
+mod email;
+mod gitlog;
+mod wizard;
+mod tui;
+mod completions;
+mod termify;
+mod pager;
+mod calendar;
+mod clock;
 
-use standup::Aspect;
+use standup::{Aspect, Standup, Manager};
 use app::App;
+use store::{Store, Retention};
+use config::{Config, Profile};
+use termify::Terminal;
+use serde_json::builder::{ArrayBuilder, ObjectBuilder};
+use serde_json::Value;
 
 static TYPES: &'static [&'static str] = &["today", "yesterday", "blocker"];
+static JSON_STYLES: &'static [&'static str] = &["compact", "pretty", "lines"];
+static OUTPUTS: &'static [&'static str] = &["text", "json"];
 
 #[derive(Debug)]
 pub enum CliError {
     Io(io::Error),
     Parse(serde_json::error::Error),
+    Csv(csv::Error),
+    Yaml(serde_yaml::Error),
+    Toml(toml::de::Error),
+    Git(String),
+    Http(String),
+    Smtp(String),
+    /// The command line could not be parsed, with clap's reason.
+    Usage(String),
     Cli(StandupError)
 }
 
@@ -27,42 +81,328 @@ pub enum StandupError {
     HomeDirNotFound,
     DataFilepathInvalid,
     InvalidDate,
+    SyncNotEnabled,
+    SyncAlreadyEnabled,
+    NoRemote,
+    JournalNotEnabled,
+    JournalAlreadyEnabled,
+    JournalNotSupported,
+    NotEncrypted,
+    AlreadyEncrypted,
+    EncryptionNotSupported,
+    EncryptionFailed,
+    WrongSecret,
+    PassphraseMismatch,
+    EmptySecret,
+    BackupsNotEnabled,
+    BackupsNotSupported,
+    BackupNotFound,
+    InvalidNumber,
+    InvalidEntry(usize),
+    NoEntries,
+    LineNotFound,
+    InvalidAspect,
+    NoStandups,
+    InvalidAddress(String),
+    InvalidConfig(String, String),
+    ProfileNotFound,
+    ProfileExists,
+    DefaultProfile,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::Io(ref err)     => write!(f, "{}", err),
+            CliError::Parse(ref err)  => write!(f, "could not read the standup data: {}", err),
+            CliError::Csv(ref err)    => write!(f, "could not write CSV: {}", err),
+            CliError::Yaml(ref err)   => write!(f, "could not write YAML: {}", err),
+            CliError::Toml(ref err)   => write!(f, "could not read the config file: {}", err),
+            CliError::Git(ref msg)    => write!(f, "{}", msg),
+            CliError::Http(ref msg)   => write!(f, "{}", msg),
+            CliError::Smtp(ref msg)   => write!(f, "could not send the email: {}", msg),
+            CliError::Usage(ref msg)  => write!(f, "{}", msg),
+            CliError::Cli(ref err)    => write!(f, "{}", err),
+        }
+    }
+}
+
+impl fmt::Display for StandupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match *self {
+            StandupError::HomeDirNotFound         => "could not find your home directory",
+            StandupError::DataFilepathInvalid     => "the data file path is not valid",
+            StandupError::InvalidDate             => "dates must look like YYYY-MM-DD, or be today, yesterday, tomorrow or workdays away like -2",
+            StandupError::SyncNotEnabled          => "sync is not set up, run `standup sync init` first",
+            StandupError::SyncAlreadyEnabled      => "sync is already set up",
+            StandupError::NoRemote                => "the standup repository has no remote to sync with",
+            StandupError::JournalNotEnabled       => "journaling is not enabled",
+            StandupError::JournalAlreadyEnabled   => "journaling is already enabled",
+            StandupError::JournalNotSupported     => "journaling only works with a plain data file",
+            StandupError::NotEncrypted            => "the data file is not encrypted",
+            StandupError::AlreadyEncrypted        => "the data file is already encrypted",
+            StandupError::EncryptionNotSupported  => "encryption only works with a plain data file",
+            StandupError::EncryptionFailed        => "could not encrypt the data file",
+            StandupError::WrongSecret             => "wrong passphrase or key file, or the data file has been tampered with",
+            StandupError::PassphraseMismatch      => "the passphrases did not match",
+            StandupError::EmptySecret             => "the passphrase or key file is too short",
+            StandupError::BackupsNotEnabled       => "backups are not enabled, run `standup backup enable` first",
+            StandupError::BackupsNotSupported     => "backups only work with a single data file",
+            StandupError::BackupNotFound          => "there is no backup with that id, see `standup backup list`",
+            StandupError::InvalidNumber           => "expected a whole number",
+            StandupError::NoEntries               => "there were no entries to record",
+            StandupError::LineNotFound            => "there is no entry with that line number",
+            StandupError::InvalidAspect           => "the type must be today, yesterday or blocker",
+            StandupError::NoStandups              => "there are no standups to send",
+            StandupError::ProfileNotFound         => "there is no profile with that name, see `standup profile list`",
+            StandupError::ProfileExists           => "a profile with that name already exists",
+            StandupError::DefaultProfile          => "the default profile cannot be renamed or deleted",
+            StandupError::InvalidConfig(ref key, ref reason) => {
+                return write!(f, "config key `{}` {}", key, reason);
+            },
+            StandupError::InvalidEntry(line) => {
+                return write!(f, "line {} must start with t:, y: or b:", line);
+            },
+            StandupError::InvalidAddress(ref address) => {
+                return write!(f, "`{}` is not a valid email address", address);
+            },
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl CliError {
+    /// A stable name for the error, for `--output json`.
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            CliError::Io(_)     => "io",
+            CliError::Parse(_)  => "parse",
+            CliError::Csv(_)    => "csv",
+            CliError::Yaml(_)   => "yaml",
+            CliError::Toml(_)   => "toml",
+            CliError::Git(_)    => "git",
+            CliError::Http(_)   => "http",
+            CliError::Smtp(_)   => "smtp",
+            CliError::Usage(_)  => "usage",
+            CliError::Cli(ref err) => err.kind(),
+        }
+    }
+}
+
+impl StandupError {
+    pub fn kind(&self) -> &'static str {
+        match *self {
+            StandupError::HomeDirNotFound        => "home_dir_not_found",
+            StandupError::DataFilepathInvalid    => "data_filepath_invalid",
+            StandupError::InvalidDate            => "invalid_date",
+            StandupError::SyncNotEnabled         => "sync_not_enabled",
+            StandupError::SyncAlreadyEnabled     => "sync_already_enabled",
+            StandupError::NoRemote               => "no_remote",
+            StandupError::JournalNotEnabled      => "journal_not_enabled",
+            StandupError::JournalAlreadyEnabled  => "journal_already_enabled",
+            StandupError::JournalNotSupported    => "journal_not_supported",
+            StandupError::NotEncrypted           => "not_encrypted",
+            StandupError::AlreadyEncrypted       => "already_encrypted",
+            StandupError::EncryptionNotSupported => "encryption_not_supported",
+            StandupError::EncryptionFailed       => "encryption_failed",
+            StandupError::WrongSecret            => "wrong_secret",
+            StandupError::PassphraseMismatch     => "passphrase_mismatch",
+            StandupError::EmptySecret            => "empty_secret",
+            StandupError::BackupsNotEnabled      => "backups_not_enabled",
+            StandupError::BackupsNotSupported    => "backups_not_supported",
+            StandupError::BackupNotFound         => "backup_not_found",
+            StandupError::InvalidNumber          => "invalid_number",
+            StandupError::InvalidEntry(..)       => "invalid_entry",
+            StandupError::NoEntries              => "no_entries",
+            StandupError::LineNotFound           => "line_not_found",
+            StandupError::InvalidAspect          => "invalid_aspect",
+            StandupError::NoStandups             => "no_standups",
+            StandupError::InvalidAddress(..)     => "invalid_address",
+            StandupError::InvalidConfig(..)      => "invalid_config",
+            StandupError::ProfileNotFound        => "profile_not_found",
+            StandupError::ProfileExists          => "profile_exists",
+            StandupError::DefaultProfile         => "default_profile",
+        }
+    }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    let config = Config::load(config::find_arg(&args, "--config").as_ref().map(|s| s.as_str()));
+    let profile_name = config::find_arg(&args, "--profile");
+    let (config, profile) = match config.and_then(|config| {
+        let profile = try!(config.profile(profile_name.as_ref().map(|s| s.as_str()))).clone();
+        Ok((config, profile))
+    }) {
+        Ok(loaded) => loaded,
+        Err(err) => fail(&args, err),
+    };
+    let args = config.expand_alias(args);
+
+    // Usage errors go through `fail` too, so they come out as JSON when asked.
+    let cli = if json_requested(&args) { cli().global_setting(AppSettings::ColorNever) } else { cli() };
+    let matches = match cli.get_matches_from_safe(args.clone()) {
+        Ok(matches) => matches,
+        Err(err) => {
+            let shown = err.kind == clap::ErrorKind::HelpDisplayed || err.kind == clap::ErrorKind::VersionDisplayed;
+            if shown || !json_requested(&args) {
+                err.exit();
+            }
+            let reason = err.message.lines().next().unwrap_or("").trim_start_matches("error: ").to_string();
+            fail(&args, CliError::Usage(reason))
+        },
+    };
+
+    let result = match matches.subcommand() {
+        ("add",         Some(sub_args)) => handle_add(&profile, sub_args),
+        ("calendar",    Some(sub_args)) => handle_calendar(&profile, sub_args),
+        ("pto",         Some(sub_args)) => handle_pto(&config, &profile, sub_args),
+        ("completions", Some(sub_args)) => handle_completions(sub_args),
+        ("complete",    Some(sub_args)) => handle_complete(&profile, sub_args),
+        ("today",       Some(sub_args)) => record_message(&profile, Aspect::Today, sub_args),
+        ("yesterday",   Some(sub_args)) => record_message(&profile, Aspect::Yesterday, sub_args),
+        ("blocker",     Some(sub_args)) => record_message(&profile, Aspect::Blocker, sub_args),
+        ("tui",         Some(sub_args)) => handle_tui(&profile, sub_args),
+        ("new",         Some(sub_args)) => handle_new(&profile, sub_args),
+        ("",            None)           => handle_new(&profile, &ArgMatches::default()),
+        ("show",        Some(sub_args)) => handle_show(&profile, sub_args),
+        ("list",        Some(sub_args)) => handle_list(&profile, sub_args),
+        ("delete",      Some(sub_args)) => handle_delete(&profile, sub_args),
+        ("move",        Some(sub_args)) => handle_move(&profile, sub_args),
+        ("export",      Some(sub_args)) => handle_export(&config, &profile, sub_args),
+        ("merge",       Some(sub_args)) => handle_merge(&profile, sub_args),
+        ("sync",        Some(sub_args)) => handle_sync(&profile, sub_args),
+        ("format",      Some(sub_args)) => handle_format(&profile, sub_args),
+        ("journal",     Some(sub_args)) => handle_journal(&profile, sub_args),
+        ("encrypt",     Some(sub_args)) => handle_encrypt(&profile, sub_args),
+        ("decrypt",     Some(sub_args)) => handle_decrypt(&profile, sub_args),
+        ("backup",      Some(sub_args)) => handle_backup(&profile, sub_args),
+        ("config",      Some(sub_args)) => handle_config(&config, sub_args),
+        ("profile",     Some(sub_args)) => handle_profile(&config, &profile, sub_args),
+        ("email",       Some(sub_args)) => handle_email(&profile, sub_args),
+        ("import-git",  Some(sub_args)) => handle_import_git(&profile, sub_args),
+        ("post",        Some(sub_args)) => handle_post(&profile, sub_args),
+        ("publish",     Some(sub_args)) => handle_publish(&config, &profile, sub_args),
+        ("serve",       Some(sub_args)) => handle_serve(&profile, sub_args),
+        _ => Ok(()),
+    };
+    if let Err(err) = result {
+        fail(&args, err);
+    }
+}
+
+/// Whether `--output json` is in the raw arguments, for before they are
+/// parsed or when they could not be.
+fn json_requested(args: &[String]) -> bool {
+    config::find_arg(args, "--output").map_or(false, |output| output == "json")
+}
+
+/// Reports the error on stderr, as JSON with `--output json`, and exits.
+fn fail(args: &[String], err: CliError) -> ! {
+    if json_requested(args) {
+        write!(io::stderr(), "{}", jsonify::serialize_error(&err.to_string(), err.kind())).unwrap();
+    } else {
+        writeln!(io::stderr(), "error: {}", err).unwrap();
+    }
+    process::exit(1);
+}
+
+/// The command line, also used to generate shell completions.
+fn cli() -> clap::App<'static, 'static> {
     let date_arg = Arg::with_name("date")
         .short("d")
         .long("date")
         .value_name("DATE")
         .use_delimiter(false)
-        .help("The date that the standup happens on");
+        .allow_hyphen_values(true)
+        .help("The date that the standup happens on, as YYYY-MM-DD, today, yesterday, tomorrow or workdays away like -2");
     let message_arg = Arg::with_name("message")
         .value_name("MESSAGE")
-        .required(true)
+        .multiple(true)
+        .required_unless("stdin")
         .use_delimiter(false)
-        .help("The message to add to the stand up");
+        .help("The messages to add to the stand up, each as its own entry");
+    let stdin_arg = Arg::with_name("stdin")
+        .long("stdin")
+        .conflicts_with("message")
+        .help("Reads the messages from stdin, one per line");
+    let as_arg = Arg::with_name("as")
+        .long("as")
+        .value_name("NAME")
+        .help("Whose standup this is in a shared log, team.identity in the config by default");
 
-    let matches = clap::App::new("standup")
+    clap::App::new("standup")
         .version("0.0.1")
         .author("Kevin Bacha <chewbacha@gmail.com>")
         .about("Manages stand up entries and keeps log")
+        .arg(Arg::with_name("config")
+                 .long("config")
+                 .value_name("FILE")
+                 .global(true)
+                 .help("Reads settings from this file instead of ~/.config/standup/config.toml"))
+        .arg(Arg::with_name("profile")
+                 .long("profile")
+                 .value_name("NAME")
+                 .global(true)
+                 .help("Uses this profile instead of the one switched to"))
+        .arg(Arg::with_name("no_pager")
+                 .long("no-pager")
+                 .global(true)
+                 .help("Prints long listings straight to the terminal instead of through a pager"))
+        .arg(Arg::with_name("output")
+                 .long("output")
+                 .value_name("FORMAT")
+                 .possible_values(&OUTPUTS)
+                 .global(true)
+                 .help("Prints text, or JSON for scripts (text by default)"))
+        .arg(Arg::with_name("color")
+                 .long("color")
+                 .value_name("WHEN")
+                 .possible_values(&termify::COLORS)
+                 .global(true)
+                 .help("Colours the output: always, never or auto (when printing to a terminal and NO_COLOR is not set)"))
         .subcommand(SubCommand::with_name("today")
                         .about("Manages what you will be working on")
                         .alias("t")
                         .arg(date_arg.clone())
                         .arg(message_arg.clone())
+                        .arg(stdin_arg.clone())
+                        .arg(as_arg.clone())
+                        )
+        .subcommand(SubCommand::with_name("tui")
+                        .about("Browses and edits standups in a full screen view")
+                        .arg(date_arg.clone())
+                        .arg(as_arg.clone())
                         )
         .subcommand(SubCommand::with_name("yesterday")
                         .about("Manages what you worked on the day before")
                         .alias("y")
                         .arg(date_arg.clone())
                         .arg(message_arg.clone())
+                        .arg(stdin_arg.clone())
+                        .arg(as_arg.clone())
                         )
         .subcommand(SubCommand::with_name("blocker")
                         .about("Manages what is blocking you")
                         .alias("b")
                         .arg(date_arg.clone())
                         .arg(message_arg.clone())
+                        .arg(stdin_arg.clone())
+                        .arg(as_arg.clone())
+                        )
+        .subcommand(SubCommand::with_name("add")
+                        .about("Adds entries of any type, written one per line as t:, y: or b: followed by the message")
+                        .arg(date_arg.clone())
+                        .arg(as_arg.clone())
+                        .arg(Arg::with_name("file")
+                                 .value_name("FILE")
+                                 .index(1)
+                                 .help("The file to read the entries from, stdin by default."))
+                        )
+        .subcommand(SubCommand::with_name("new")
+                        .about("Asks for each part of the standup in turn, the same as running with no subcommand")
+                        .arg(date_arg.clone())
+                        .arg(as_arg.clone())
                         )
         .subcommand(SubCommand::with_name("show")
                         .about("Displays the notes from stand up")
@@ -77,6 +417,7 @@ fn main() {
                         .about("Deletes the standup on the specified day.")
                         .alias("d")
                         .arg(date_arg.clone().required(true))
+                        .arg(as_arg.clone())
                         .arg(Arg::with_name("type")
                                  .value_name("TYPE")
                                  .requires("line_number")
@@ -89,59 +430,1102 @@ fn main() {
                                  .index(2)
                                  .help("The line number to delete."))
                         )
-        .get_matches();
+        .subcommand(SubCommand::with_name("move")
+                        .about("Moves an entry within its type, to another type or to another day")
+                        .alias("mv")
+                        .arg(date_arg.clone())
+                        .arg(as_arg.clone())
+                        .arg(Arg::with_name("type")
+                                 .value_name("TYPE")
+                                 .required(true)
+                                 .possible_values(&TYPES)
+                                 .index(1)
+                                 .help("The type of the line to move."))
+                        .arg(Arg::with_name("line_number")
+                                 .value_name("LINE_NUMBER")
+                                 .required(true)
+                                 .index(2)
+                                 .help("The line number to move."))
+                        .arg(Arg::with_name("to_type")
+                                 .long("to-type")
+                                 .value_name("TYPE")
+                                 .possible_values(&TYPES)
+                                 .help("Moves the line to this type, the same type by default"))
+                        .arg(Arg::with_name("to_line")
+                                 .long("to-line")
+                                 .value_name("LINE_NUMBER")
+                                 .conflicts_with("to_date")
+                                 .help("Puts the line at this line number instead of at the end"))
+                        .arg(Arg::with_name("to_date")
+                                 .long("to-date")
+                                 .value_name("DATE")
+                                 .help("Moves the line to the end of the standup on this day"))
+                        .group(ArgGroup::with_name("target")
+                                   .args(&["to_type", "to_line", "to_date"])
+                                   .multiple(true)
+                                   .required(true))
+                        )
+        .subcommand(SubCommand::with_name("export")
+                        .about("Exports standups as CSV, YAML or iCalendar")
+                        .arg(Arg::with_name("format")
+                                 .value_name("FORMAT")
+                                 .possible_values(&config::EXPORT_FORMATS)
+                                 .index(1)
+                                 .help("The format to export to, export.format in the config by default."))
+                        .arg(Arg::with_name("from")
+                                 .long("from")
+                                 .value_name("DATE")
+                                 .help("Only export standups on or after this date"))
+                        .arg(Arg::with_name("to")
+                                 .long("to")
+                                 .value_name("DATE")
+                                 .help("Only export standups on or before this date"))
+                        .arg(Arg::with_name("out")
+                                 .short("o")
+                                 .long("out")
+                                 .value_name("FILE")
+                                 .help("Writes the export to a file instead of stdout"))
+                        )
+        .subcommand(SubCommand::with_name("merge")
+                        .about("Merges the standups from another data file into this one")
+                        .arg(Arg::with_name("file")
+                                 .value_name("FILE")
+                                 .required(true)
+                                 .index(1)
+                                 .help("The standup file to merge in."))
+                        )
+        .subcommand(SubCommand::with_name("sync")
+                        .about("Pulls and pushes standups through a git remote")
+                        .subcommand(SubCommand::with_name("init")
+                                        .about("Moves the standups into a git repository next to the data file")
+                                        .arg(Arg::with_name("remote")
+                                                 .long("remote")
+                                                 .value_name("URL")
+                                                 .help("A git remote to clone and sync with")))
+                        )
+        .subcommand(SubCommand::with_name("format")
+                        .about("Rewrites the data file in another JSON style")
+                        .arg(Arg::with_name("style")
+                                 .value_name("STYLE")
+                                 .required(true)
+                                 .possible_values(&JSON_STYLES)
+                                 .index(1)
+                                 .help("compact, pretty (indented) or lines (one standup per line)"))
+                        )
+        .subcommand(SubCommand::with_name("journal")
+                        .about("Appends changes to a journal instead of rewriting the whole file")
+                        .subcommand(SubCommand::with_name("enable")
+                                        .about("Starts journaling changes"))
+                        .subcommand(SubCommand::with_name("disable")
+                                        .about("Folds the journal into the file and stops journaling"))
+                        .subcommand(SubCommand::with_name("compact")
+                                        .about("Folds the journal into the file now"))
+                        )
+        .subcommand(SubCommand::with_name("encrypt")
+                        .about("Encrypts the data file with a passphrase or key file")
+                        )
+        .subcommand(SubCommand::with_name("decrypt")
+                        .about("Stores the data file as plain JSON again")
+                        )
+        .subcommand(SubCommand::with_name("backup")
+                        .about("Keeps backups of the data file and restores them")
+                        .subcommand(SubCommand::with_name("enable")
+                                        .about("Starts backing up the data file before every change")
+                                        .arg(Arg::with_name("keep_last")
+                                                 .long("keep-last")
+                                                 .value_name("N")
+                                                 .help("Always keep the newest N backups (default 10)"))
+                                        .arg(Arg::with_name("daily")
+                                                 .long("daily")
+                                                 .value_name("DAYS")
+                                                 .help("Keep one backup a day for this many days (default 7)"))
+                                        .arg(Arg::with_name("weekly")
+                                                 .long("weekly")
+                                                 .value_name("WEEKS")
+                                                 .help("Keep one backup a week for this many weeks (default 4)")))
+                        .subcommand(SubCommand::with_name("list")
+                                        .about("Lists the backups"))
+                        .subcommand(SubCommand::with_name("restore")
+                                        .about("Replaces the data file with a backup")
+                                        .arg(Arg::with_name("id")
+                                                 .value_name("ID")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The backup to restore, as shown by `backup list`."))
+                                        .arg(Arg::with_name("yes")
+                                                 .short("y")
+                                                 .long("yes")
+                                                 .help("Restores without asking for confirmation")))
+                        )
+        .subcommand(SubCommand::with_name("config")
+                        .about("Reads and changes settings in the config file")
+                        .subcommand(SubCommand::with_name("get")
+                                        .about("Prints the value of a setting")
+                                        .arg(Arg::with_name("key")
+                                                 .value_name("KEY")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The setting, such as display.date_format")))
+                        .subcommand(SubCommand::with_name("set")
+                                        .about("Changes a setting in the config file")
+                                        .arg(Arg::with_name("key")
+                                                 .value_name("KEY")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The setting, such as display.date_format"))
+                                        .arg(Arg::with_name("value")
+                                                 .value_name("VALUE")
+                                                 .required(true)
+                                                 .use_delimiter(false)
+                                                 .index(2)
+                                                 .help("The new value, lists are comma separated")))
+                        .subcommand(SubCommand::with_name("list")
+                                        .about("Prints every setting and its value"))
+                        )
+        .subcommand(SubCommand::with_name("profile")
+                        .about("Keeps separate logs for separate standups")
+                        .subcommand(SubCommand::with_name("list")
+                                        .about("Lists the profiles, marking the one in use"))
+                        .subcommand(SubCommand::with_name("create")
+                                        .about("Adds a profile with its own data file")
+                                        .arg(Arg::with_name("name")
+                                                 .value_name("NAME")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The name of the profile"))
+                                        .arg(Arg::with_name("data")
+                                                 .long("data")
+                                                 .value_name("FILE")
+                                                 .help("Where to keep its standups, ~/.standup.NAME.json by default")))
+                        .subcommand(SubCommand::with_name("switch")
+                                        .about("Uses the profile from now on")
+                                        .arg(Arg::with_name("name")
+                                                 .value_name("NAME")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The name of the profile")))
+                        .subcommand(SubCommand::with_name("rename")
+                                        .about("Renames a profile, keeping its data file")
+                                        .arg(Arg::with_name("from")
+                                                 .value_name("NAME")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The current name of the profile"))
+                                        .arg(Arg::with_name("to")
+                                                 .value_name("NEW_NAME")
+                                                 .required(true)
+                                                 .index(2)
+                                                 .help("The new name")))
+                        .subcommand(SubCommand::with_name("delete")
+                                        .about("Removes a profile from the config, leaving its data file alone")
+                                        .arg(Arg::with_name("name")
+                                                 .value_name("NAME")
+                                                 .required(true)
+                                                 .index(1)
+                                                 .help("The name of the profile")))
+                        )
+        .subcommand(SubCommand::with_name("email")
+                        .about("Emails a day or week of standups as text and HTML")
+                        .arg(date_arg.clone())
+                        .arg(Arg::with_name("week")
+                                 .short("w")
+                                 .long("week")
+                                 .help("Sends the whole week the date falls in"))
+                        .arg(Arg::with_name("to")
+                                 .long("to")
+                                 .value_name("ADDRESS")
+                                 .multiple(true)
+                                 .number_of_values(1)
+                                 .help("Who to send it to, email.to in the config by default"))
+                        .arg(Arg::with_name("out")
+                                 .short("o")
+                                 .long("out")
+                                 .value_name("FILE")
+                                 .help("Writes the email to an .eml file instead of sending it"))
+                        )
+        .subcommand(SubCommand::with_name("import-git")
+                        .about("Suggests yesterday entries from your commits since the previous workday")
+                        .arg(date_arg.clone())
+                        .arg(Arg::with_name("yes")
+                                 .short("y")
+                                 .long("yes")
+                                 .help("Adds every commit without asking"))
+                        )
+        .subcommand(SubCommand::with_name("post")
+                        .about("Posts the standup to a chat through an incoming webhook")
+                        .arg(date_arg.clone())
+                        .arg(as_arg.clone())
+                        .arg(Arg::with_name("webhook")
+                                 .long("webhook")
+                                 .value_name("URL")
+                                 .help("The webhook to post to, post.webhook in the config by default"))
+                        .arg(Arg::with_name("chat")
+                                 .long("chat")
+                                 .value_name("CHAT")
+                                 .possible_values(&chatify::CHATS)
+                                 .help("The chat the webhook belongs to, post.chat in the config by default"))
+                        .arg(Arg::with_name("dry_run")
+                                 .long("dry-run")
+                                 .help("Prints the message instead of posting it"))
+                        )
+        .subcommand(SubCommand::with_name("publish")
+                        .about("Renders the standups as a static HTML site")
+                        .arg(Arg::with_name("out")
+                                 .short("o")
+                                 .long("out")
+                                 .value_name("DIR")
+                                 .required(true)
+                                 .help("The directory to write the site into"))
+                        )
+        .subcommand(SubCommand::with_name("calendar")
+                        .about("Shows the workdays, holidays, time off and days with entries in a month")
+                        .arg(date_arg.clone().help("A day in the month to show, today by default"))
+                        .arg(as_arg.clone())
+                        )
+        .subcommand(SubCommand::with_name("pto")
+                        .about("Keeps track of time off, which is skipped when looking for the previous workday")
+                        .subcommand(SubCommand::with_name("add")
+                                        .about("Adds time off, from one day to another or for a single day")
+                                        .arg(Arg::with_name("from")
+                                                 .value_name("FROM")
+                                                 .required(true)
+                                                 .allow_hyphen_values(true)
+                                                 .index(1)
+                                                 .help("The first day off."))
+                                        .arg(Arg::with_name("to")
+                                                 .value_name("TO")
+                                                 .allow_hyphen_values(true)
+                                                 .index(2)
+                                                 .help("The last day off, the first day by default.")))
+                        .subcommand(SubCommand::with_name("list")
+                                        .about("Lists the time off"))
+                        )
+        .subcommand(SubCommand::with_name("completions")
+                        .about("Prints a completion script for the shell")
+                        .arg(Arg::with_name("shell")
+                                 .value_name("SHELL")
+                                 .required(true)
+                                 .possible_values(completions::SHELLS)
+                                 .help("bash, zsh or fish"))
+                        )
+        .subcommand(SubCommand::with_name("complete")
+                        .about("Prints the dates or line numbers that could come next, for the completion scripts")
+                        .setting(AppSettings::Hidden)
+                        .arg(Arg::with_name("shell")
+                                 .value_name("SHELL")
+                                 .required(true)
+                                 .possible_values(completions::SHELLS))
+                        .arg(Arg::with_name("words")
+                                 .value_name("WORDS")
+                                 .multiple(true)
+                                 .last(true))
+                        )
+        .subcommand(SubCommand::with_name("serve")
+                        .about("Serves the standups as JSON on localhost")
+                        .arg(Arg::with_name("port")
+                                 .short("p")
+                                 .long("port")
+                                 .value_name("PORT")
+                                 .help("The port to listen on (default 7878)"))
+                        )
+}
+
+fn record_message(profile: &Profile, aspect: Aspect, args: &ArgMatches) -> Result<(), CliError> {
+    let messages: Vec<String> = if args.is_present("stdin") {
+        let mut text = String::new();
+        try!(io::stdin().read_to_string(&mut text).map_err(CliError::Io));
+        text.lines().map(|line| line.trim()).filter(|line| !line.is_empty()).map(|line| line.to_string()).collect()
+    } else {
+        args.values_of("message").unwrap().map(|s| s.to_string()).collect()
+    };
+    if messages.is_empty() {
+        return Err(CliError::Cli(StandupError::NoEntries));
+    }
+    let date = args.value_of("date").map(|s| s.to_string());
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    try!(match messages.len() {
+        1 => app.record(aspect, messages[0].clone()),
+        _ => app.record_all(aspect, &messages),
+    });
+    if json(args) {
+        print!("{}", jsonify::serialize_day(&app.get_standup()));
+    }
+    Ok(())
+}
+
+fn handle_add(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let mut text = String::new();
+    try!(match args.value_of("file") {
+        Some(path) if path != "-" => File::open(path).and_then(|mut file| file.read_to_string(&mut text)),
+        _ => io::stdin().read_to_string(&mut text),
+    }.map_err(CliError::Io));
+    let entries = try!(standup::parse_entries(&text).map_err(CliError::Cli));
+    if entries.is_empty() {
+        return Err(CliError::Cli(StandupError::NoEntries));
+    }
+    let date = args.value_of("date").map(|s| s.to_string());
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    try!(app.record_entries(&entries));
+    if json(args) {
+        print!("{}", jsonify::serialize_day(&app.get_standup()));
+    } else {
+        println!("Added {} entries", entries.len());
+    }
+    Ok(())
+}
+
+/// Nothing is written until the last question has been answered, so Ctrl-C
+/// or Ctrl-D part way through leaves the log as it was.
+fn handle_new(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let date = args.value_of("date").map(|s| s.to_string());
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    let standup = app.get_standup();
+    let previous = app.previous_standup();
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    let (mut input, mut output) = (stdin.lock(), stdout.lock());
+    let finished = try!(wizard::Wizard::new(&mut input, &mut output).run(standup.clone(), previous.as_ref(), app.layout()));
+    match finished {
+        Some(ref finished) if *finished == standup => println!("Nothing to change"),
+        Some(finished) => {
+            println!("\n{}", terminal(args).render(app.layout(), &finished));
+            try!(app.save(finished));
+        },
+        None => println!("\nStopped, nothing was saved"),
+    }
+    Ok(())
+}
+
+fn handle_tui(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let date = args.value_of("date").map(|s| s.to_string());
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    tui::run(app)
+}
 
-    match matches.subcommand() {
-        ("today",       Some(sub_args)) => record_message(Aspect::Today, sub_args),
-        ("yesterday",   Some(sub_args)) => record_message(Aspect::Yesterday, sub_args),
-        ("blocker",     Some(sub_args)) => record_message(Aspect::Blocker, sub_args),
-        ("show",        Some(sub_args)) => handle_show(sub_args),
-        ("list",        Some(_sub_args)) => handle_list(),
-        ("delete",      Some(sub_args)) => handle_delete(sub_args),
-        _ => {},
+fn handle_show(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let date = args.value_of("date").map(|s| s.to_string());
+    let app = try!(App::new(profile, date));
+    let standups = app.day();
+    if json(args) {
+        let missing = app.missing(profile.clock.now());
+        print!("{}", jsonify::serialize_show(&app.date(), &standups.iter().collect::<Vec<&Standup>>(), &missing));
+        return Ok(());
+    }
+    let mut out = format!("{}\n", terminal(args).render_day(app.layout(), &standups.iter().collect::<Vec<&Standup>>()));
+    let missing = app.missing(profile.clock.now());
+    if let (false, Some(deadline)) = (missing.is_empty(), app.deadline()) {
+        out.push_str(&format!("Not posted by {}: {}\n", deadline.format("%H:%M"), missing.join(", ")));
     }
+    page(profile, args, &out)
 }
 
-fn record_message(aspect: Aspect, args: &ArgMatches) {
-    let message = args.value_of("message").map(|s| s.to_string()).unwrap();
+fn handle_list(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let app = try!(App::new(profile, None));
+    if json(args) {
+        let days = app.days_between(None, None);
+        let standups: Vec<&Standup> = days.iter().rev().flat_map(|day| day.iter().cloned()).collect();
+        print!("{}", jsonify::serialize_with(&standups, jsonify::Style::Pretty));
+        return Ok(());
+    }
+    let terminal = terminal(args);
+    let out: String = app.days_between(None, None).iter().rev()
+        .map(|day| format!("{}\n", terminal.render_day(app.layout(), day)))
+        .collect();
+    page(profile, args, &out)
+}
+
+fn handle_move(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
     let date = args.value_of("date").map(|s| s.to_string());
-    let mut app = App::new(date).unwrap();
-    app.record(aspect, message);
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    let aspect = Aspect::from_name(args.value_of("type").unwrap()).unwrap();
+    let index = try!(parse_line(args.value_of("line_number").unwrap()));
+    let target = args.value_of("to_type").and_then(Aspect::from_name).unwrap_or(aspect);
+    if let Some(to_date) = args.value_of("to_date") {
+        let to_date = try!(app.resolve_date(to_date));
+        try!(app.move_to_date(aspect, index, to_date, target));
+        if !json(args) {
+            println!("{}", terminal(args).render_day(app.layout(), &[&app.get_standup()]));
+        }
+        app.set_date(to_date);
+    } else {
+        let to_index = match args.value_of("to_line") {
+            Some(line) => Some(try!(parse_line(line))),
+            None => None,
+        };
+        try!(app.move_line(aspect, index, target, to_index));
+    }
+    print_standup(args, &app, &app.get_standup());
+    Ok(())
 }
 
-fn handle_show(args: &ArgMatches) {
+fn handle_calendar(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
     let date = args.value_of("date").map(|s| s.to_string());
-    let app = App::new(date).unwrap();
-    println!("{}", &app.get_standup());
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    let month = app.date();
+    let days = app.calendar().month(month).into_iter().fold(ArrayBuilder::new(), |builder, date| {
+        let day = app.calendar().day(date);
+        builder.push_object(|object| {
+            let object = object
+                .insert("date", date.format("%F").to_string())
+                .insert("kind", day.name())
+                .insert("entries", app.has_entries(date));
+            match day {
+                calendar::Day::Holiday(name) => object.insert("name", name),
+                _ => object,
+            }
+        })
+    });
+    let text = app.calendar().render_month(month, |date| app.has_entries(date));
+    let today = app.today();
+    app.set_date(today);
+    if json(args) {
+        print_json(ObjectBuilder::new()
+            .insert("month", month.format("%Y-%m").to_string())
+            .insert("days", days.unwrap())
+            .insert("streak", app.streak())
+            .unwrap());
+        return Ok(());
+    }
+    print!("{}", text);
+    println!("Streak: {} workdays", app.streak());
+    Ok(())
 }
 
-fn handle_list() {
-    let app = App::new(None).unwrap();
-    for standup in app.standups().iter().rev() {
-        println!("{}", &standup);
+fn handle_pto(config: &Config, profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let app = try!(App::new(profile, None));
+    match args.subcommand() {
+        ("add", Some(add_args)) => {
+            let from = try!(app.resolve_date(add_args.value_of("from").unwrap()));
+            let to = match add_args.value_of("to") {
+                Some(to) => try!(app.resolve_date(to)),
+                None => from,
+            };
+            if to < from {
+                return Err(CliError::Cli(StandupError::InvalidConfig(
+                    "calendar.pto".to_string(), "cannot end before it starts".to_string())));
+            }
+            try!(config.add_pto(&profile.name, from, to));
+            if json(args) {
+                print_json(range_object(&(from, to)));
+            } else {
+                println!("Off {}", config::format_range(&(from, to)));
+            }
+        },
+        _ if json(args) => {
+            print_json(Value::Array(profile.pto.iter().map(range_object).collect()));
+        },
+        _ => {
+            for range in profile.pto.iter() {
+                println!("{}", config::format_range(range));
+            }
+        },
+    }
+    Ok(())
+}
+
+fn handle_completions(args: &ArgMatches) -> Result<(), CliError> {
+    let shell = args.value_of("shell").unwrap().parse::<Shell>().unwrap();
+    print!("{}", completions::script(cli(), shell));
+    Ok(())
+}
+
+fn handle_complete(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let shell = args.value_of("shell").unwrap().parse::<Shell>().unwrap();
+    let words: Vec<String> = args.values_of("words").map_or(vec![], |words| words.map(|s| s.to_string()).collect());
+    let wanted = match completions::wanted(&words) {
+        Some(wanted) => wanted,
+        None => return Ok(()),
+    };
+    // The shell hides anything written to stderr, so a passphrase prompt
+    // would just hang the completion.
+    if try!(Store::is_locked(&profile.data_path)) && !crypto::Secret::in_env() {
+        return Ok(());
     }
+    match wanted {
+        completions::Wanted::Date => {
+            let app = try!(App::new(profile, None));
+            let mut dates: Vec<_> = app.standups().iter().map(|standup| standup.date).collect();
+            dates.sort_by(|a, b| b.cmp(a));
+            dates.dedup();
+            for date in dates {
+                println!("{}", completions::candidate(shell, &date.format("%F").to_string(),
+                                                      &date.format("%A").to_string()));
+            }
+        },
+        completions::Wanted::Line { aspect, date, author } => {
+            let mut app = try!(App::new(profile, date));
+            app.set_author(author);
+            for (index, message) in app.get_standup().messages(aspect).iter().enumerate() {
+                println!("{}", completions::candidate(shell, &(index + 1).to_string(), message));
+            }
+        },
+    }
+    Ok(())
 }
 
-fn handle_delete(args: &ArgMatches) {
+fn handle_delete(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
     let date = args.value_of("date").map(|s| s.to_string());
-    let mut app = App::new(date).unwrap();
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
     if let Some(line_number) = args.value_of("line_number") {
-        if let Ok(index) = line_number.parse::<usize>() {
-            match args.value_of("type") {
-                Some("today")       => app.delete_line(Aspect::Today,       index - 1),
-                Some("yesterday")   => app.delete_line(Aspect::Yesterday,   index - 1),
-                Some("blocker")     => app.delete_line(Aspect::Blocker,     index - 1),
-                _                   => println!("Invalid aspect")
-            }
-            println!("{}", &app.get_standup());
+        let index = try!(parse_line(line_number));
+        let aspect = try!(args.value_of("type").and_then(Aspect::from_name)
+                          .ok_or(CliError::Cli(StandupError::InvalidAspect)));
+        try!(app.delete_line(aspect, index));
+        print_standup(args, &app, &app.get_standup());
+    } else {
+        let deleted = try!(app.delete());
+        if json(args) {
+            println!("{}", deleted.map_or("null".to_string(), |standup| jsonify::serialize_day(&standup)).trim_end());
+        } else if let Some(standup) = deleted {
+            println!("deleted: \n{}", terminal(args).render_day(app.layout(), &[&standup]));
         } else {
-            println!("Invalid line number");
+            println!("No standup found on that day");
+        }
+    }
+    Ok(())
+}
+
+fn handle_export(config: &Config, profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let app = try!(App::new(profile, None));
+    let from = match args.value_of("from") {
+        Some(s) => Some(try!(app.resolve_date(s))),
+        None => None,
+    };
+    let to = match args.value_of("to") {
+        Some(s) => Some(try!(app.resolve_date(s))),
+        None => None,
+    };
+    let standups = app.standups_between(from, to);
+    let format = args.value_of("format").unwrap_or(&config.export_format);
+    let output = try!(match format {
+        "csv"     => csvify::serialize(&standups).map_err(CliError::Csv),
+        "yaml"    => yamlify::serialize(&standups).map_err(CliError::Yaml),
+        _               => Ok(icalify::serialize(&standups)),
+    });
+    match args.value_of("out") {
+        Some(path) => {
+            try!(File::create(path)
+                .and_then(|mut file| file.write_all(output.as_bytes()))
+                .map_err(CliError::Io));
+            if json(args) {
+                print_json(ObjectBuilder::new().insert("written", path).insert("standups", standups.len()).unwrap());
+            }
+            Ok(())
+        },
+        None => {
+            print!("{}", output);
+            Ok(())
+        },
+    }
+}
+
+fn handle_merge(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let other = try!(OpenOptions::new()
+        .read(true)
+        .open(args.value_of("file").unwrap())
+        .map_err(CliError::Io)
+        .and_then(Manager::from_reader));
+    let mut app = try!(App::new(profile, None));
+    let conflicts = try!(app.merge(other));
+    if json(args) {
+        let conflicts = conflicts.iter().fold(ArrayBuilder::new(), |builder, conflict| {
+            builder.push_object(|object| object
+                .insert("date", conflict.date.format("%F").to_string())
+                .insert("aspect", conflict.aspect.to_string()))
+        });
+        print_json(ObjectBuilder::new().insert("conflicts", conflicts.unwrap()).unwrap());
+        return Ok(());
+    }
+    for conflict in conflicts.iter() {
+        println!("conflict: {} {} was changed in both files, kept entries from both",
+                 conflict.date.format("%F"), conflict.aspect);
+    }
+    println!("Merged with {} conflict(s)", conflicts.len());
+    Ok(())
+}
+
+fn handle_sync(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let mut app = try!(App::new(profile, None));
+    match args.subcommand() {
+        ("init", Some(init_args)) => {
+            let remote = init_args.value_of("remote").map(|s| s.to_string());
+            try!(app.enable_sync(remote));
+            let dir = Store::git_dir(&profile.data_path);
+            if json(args) {
+                print_json(ObjectBuilder::new().insert("repository", dir.display().to_string()).unwrap());
+            } else {
+                println!("Standups are now kept in a git repository in {}", dir.display());
+            }
+        },
+        _ => {
+            try!(app.sync());
+            if json(args) {
+                print_json(ObjectBuilder::new().insert("synced", app.standups().len()).unwrap());
+            } else {
+                println!("Synced {} standups", app.standups().len());
+            }
         }
+    }
+    Ok(())
+}
+
+fn handle_format(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let style = args.value_of("style").and_then(jsonify::Style::from_name).unwrap();
+    let mut app = try!(App::new(profile, None));
+    try!(app.set_style(style));
+    if json(args) {
+        print_json(ObjectBuilder::new().insert("style", style.name()).unwrap());
+    }
+    Ok(())
+}
+
+fn handle_journal(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let mut app = try!(App::new(profile, None));
+    let journal = match args.subcommand_name() {
+        Some("enable")  => { try!(app.enable_journal()); "enabled" },
+        Some("disable") => { try!(app.disable_journal()); "disabled" },
+        Some("compact") => { try!(app.compact()); "compacted" },
+        _               => {
+            println!("{}", args.usage());
+            return Ok(());
+        },
+    };
+    if json(args) {
+        print_json(ObjectBuilder::new().insert("journal", journal).unwrap());
+    }
+    Ok(())
+}
+
+fn handle_encrypt(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let mut app = try!(App::new(profile, None));
+    let secret = try!(crypto::Secret::obtain(true));
+    try!(app.encrypt(secret));
+    if json(args) {
+        print_json(ObjectBuilder::new().insert("encrypted", true).unwrap());
+    } else {
+        println!("The data file is now encrypted");
+    }
+    Ok(())
+}
+
+fn handle_decrypt(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let mut app = try!(App::new(profile, None));
+    try!(app.decrypt());
+    if json(args) {
+        print_json(ObjectBuilder::new().insert("encrypted", false).unwrap());
+    } else {
+        println!("The data file is now stored as plain JSON");
+    }
+    Ok(())
+}
+
+fn handle_backup(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let mut app = try!(App::new(profile, None));
+    match args.subcommand() {
+        ("enable", Some(enable_args)) => {
+            let default = Retention::default();
+            let retention = Retention {
+                keep_last: try!(parse_number(enable_args.value_of("keep_last"), default.keep_last)),
+                daily: try!(parse_number(enable_args.value_of("daily"), default.daily)),
+                weekly: try!(parse_number(enable_args.value_of("weekly"), default.weekly)),
+            };
+            try!(app.enable_backups(retention));
+            if json(args) {
+                print_json(retention_object(&retention));
+            } else {
+                println!("Backing up the data file before every change");
+            }
+        },
+        ("list", Some(_)) => {
+            let backups = try!(app.backups().ok_or(CliError::Cli(StandupError::BackupsNotEnabled)));
+            let ids = try!(backups.list());
+            let retention = backups.retention();
+            if json(args) {
+                let listed = ids.iter().rev().fold(ArrayBuilder::new(), |builder, id| {
+                    builder.push_object(|object| object
+                        .insert("id", store::format_id(id))
+                        .insert("taken", id.format("%F %T").to_string()))
+                });
+                print_json(ObjectBuilder::new()
+                    .insert("retention", retention_object(&retention))
+                    .insert("backups", listed.unwrap())
+                    .unwrap());
+                return Ok(());
+            }
+            println!("Keeping the last {}, and the newest of each of the last {} days and {} weeks",
+                     retention.keep_last, retention.daily, retention.weekly);
+            if ids.is_empty() {
+                println!("No backups yet");
+            }
+            for id in ids.iter().rev() {
+                println!("{}  {}", store::format_id(id), id.format("%F %T"));
+            }
+        },
+        ("restore", Some(restore_args)) => {
+            let id = restore_args.value_of("id").unwrap();
+            let backup = try!(app.read_backup(id));
+            let changes = store::summarize(app.manager(), &backup);
+            if json(args) {
+                let restored = !changes.is_empty() &&
+                    (restore_args.is_present("yes") || try!(confirm(args, "Restore this backup? [y/N] ")));
+                if restored {
+                    try!(app.restore_backup(id));
+                }
+                print_json(ObjectBuilder::new()
+                    .insert("id", id)
+                    .insert("restored", restored)
+                    .insert("changes", changes)
+                    .unwrap());
+                return Ok(());
+            }
+            if changes.is_empty() {
+                println!("The backup matches the current standups");
+                return Ok(());
+            }
+            println!("Restoring {} will change:", id);
+            for change in changes.iter() {
+                println!("  {}", change);
+            }
+            if restore_args.is_present("yes") || try!(confirm(args, "Restore this backup? [y/N] ")) {
+                try!(app.restore_backup(id));
+                println!("Restored {}", id);
+            }
+        },
+        _ => println!("{}", args.usage()),
+    }
+    Ok(())
+}
+
+fn handle_config(config: &Config, args: &ArgMatches) -> Result<(), CliError> {
+    match args.subcommand() {
+        ("get", Some(get_args)) => {
+            let key = get_args.value_of("key").unwrap();
+            let value = try!(config.get(key).ok_or(not_set(key)));
+            if json(get_args) {
+                println!("{}", config::to_json(&value));
+                return Ok(());
+            }
+            match value.as_str() {
+                Some(text) => println!("{}", text),
+                None => println!("{}", value),
+            }
+        },
+        ("set", Some(set_args)) => {
+            let key = set_args.value_of("key").unwrap();
+            let updated = try!(config.set(key, set_args.value_of("value").unwrap()));
+            let value = updated.get(key).unwrap();
+            if json(set_args) {
+                print_json(ObjectBuilder::new().insert("key", key).insert("value", config::to_json(&value)).unwrap());
+            } else {
+                println!("{} = {}", key, value);
+            }
+        },
+        ("list", Some(list_args)) if json(list_args) => {
+            let settings = config.list().iter()
+                .fold(ObjectBuilder::new(), |builder, (key, value)| builder.insert(key.clone(), config::to_json(value)));
+            println!("{}", serde_json::to_string_pretty(&settings.unwrap()).unwrap());
+        },
+        ("list", Some(_)) => {
+            println!("# {}", config.file.display());
+            for (key, value) in config.list() {
+                println!("{} = {}", key, value);
+            }
+        },
+        _ => println!("{}", args.usage()),
+    }
+    Ok(())
+}
+
+fn handle_profile(config: &Config, profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    match args.subcommand() {
+        ("create", Some(create_args)) => {
+            let name = create_args.value_of("name").unwrap();
+            let updated = try!(config.create_profile(name, create_args.value_of("data")));
+            let data_path = try!(updated.profile(Some(name))).data_path.clone();
+            if json(args) {
+                print_json(profile_object(name, &data_path, "created"));
+            } else {
+                println!("Created {}, keeping its standups in {}", name, data_path.display());
+            }
+        },
+        ("switch", Some(switch_args)) => {
+            let name = switch_args.value_of("name").unwrap();
+            try!(config.switch_profile(name));
+            if json(args) {
+                print_json(ObjectBuilder::new().insert("name", name).insert("status", "switched").unwrap());
+            } else {
+                println!("Switched to {}", name);
+            }
+        },
+        ("rename", Some(rename_args)) => {
+            let from = rename_args.value_of("from").unwrap();
+            let to = rename_args.value_of("to").unwrap();
+            try!(config.rename_profile(from, to));
+            if json(args) {
+                print_json(ObjectBuilder::new().insert("name", to).insert("from", from).insert("status", "renamed").unwrap());
+            } else {
+                println!("Renamed {} to {}", from, to);
+            }
+        },
+        ("delete", Some(delete_args)) => {
+            let name = delete_args.value_of("name").unwrap();
+            let data_path = try!(config.profile(Some(name))).data_path.clone();
+            try!(config.delete_profile(name));
+            if json(args) {
+                print_json(profile_object(name, &data_path, "deleted"));
+            } else {
+                println!("Deleted {}, its standups are still in {}", name, data_path.display());
+            }
+        },
+        _ if json(args) => {
+            let listed = config.profiles.iter().fold(ArrayBuilder::new(), |builder, (name, listed)| {
+                builder.push_object(|object| object
+                    .insert("name", name.clone())
+                    .insert("data", listed.data_path.display().to_string())
+                    .insert("current", *name == profile.name))
+            });
+            print_json(listed.unwrap());
+        },
+        _ => {
+            for (name, listed) in config.profiles.iter() {
+                let marker = if *name == profile.name { "*" } else { " " };
+                println!("{} {}  {}", marker, name, listed.data_path.display());
+            }
+        },
+    }
+    Ok(())
+}
+
+fn handle_email(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let date = args.value_of("date").map(|s| s.to_string());
+    let app = try!(App::new(profile, date));
+    let date = app.date();
+    let (subject, from, to) = if args.is_present("week") {
+        let monday = date - chrono::Duration::days(date.weekday().num_days_from_monday() as i64);
+        (format!("Standups for the week of {}", monday.format("%F")), monday, monday + chrono::Duration::days(6))
     } else {
-        if let Some(standup) = app.delete() {
-            println!("deleted: \n{}", standup);
+        (format!("Standup {}", date.format(&app.layout().date_format)), date, date)
+    };
+    let days = app.days_between(Some(from), Some(to));
+    if days.is_empty() {
+        return Err(CliError::Cli(StandupError::NoStandups));
+    }
+
+    let sender = try!(profile.email_from.clone().ok_or(not_set("email.from")));
+    let recipients: Vec<String> = args.values_of("to")
+        .map(|to| to.map(|s| s.to_string()).collect())
+        .unwrap_or(profile.email_to.clone());
+    if recipients.is_empty() {
+        return Err(not_set("email.to"));
+    }
+    let message = try!(email::message(&sender, &recipients, &subject, &days, app.layout()));
+
+    if let Some(out) = args.value_of("out") {
+        try!(email::write_eml(&message, Path::new(out)));
+        if json(args) {
+            print_json(ObjectBuilder::new().insert("written", out).unwrap());
         } else {
-            println!("No standup found on that day");
+            println!("Wrote {}", out);
         }
+        return Ok(());
+    }
+    let smtp = email::Smtp {
+        server: try!(profile.smtp_server.clone().ok_or(not_set("email.server"))),
+        security: profile.smtp_security,
+        username: profile.smtp_username.clone(),
+    };
+    try!(smtp.send(&message));
+    if json(args) {
+        print_json(ObjectBuilder::new().insert("sent_to", recipients).unwrap());
+    } else {
+        println!("Sent to {}", recipients.join(", "));
+    }
+    Ok(())
+}
+
+/// Looks for commits from the start of the previous workday up to the start
+/// of the standup's day, so that a Monday picks up weekend work too.
+fn handle_import_git(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let author = try!(profile.git_author.clone().ok_or(not_set("git.author")));
+    if profile.git_repos.is_empty() {
+        return Err(not_set("git.repos"));
+    }
+    let mut app = try!(App::new(profile, args.value_of("date").map(|s| s.to_string())));
+    let since = try!(app.previous_workday().or(app.date().pred_opt())
+                     .ok_or(CliError::Cli(StandupError::InvalidDate)));
+    let since = profile.clock.start_of(since);
+    let until = profile.clock.start_of(app.date());
+
+    let mut found = vec![];
+    for repo in profile.git_repos.iter() {
+        found.extend(try!(gitlog::commits(repo, &author, since, until)));
+    }
+    let commits = gitlog::unrecorded(found, &app.get_standup().yesterday);
+    if commits.is_empty() && !json(args) {
+        println!("No new commits since {}", since.format("%F"));
+        return Ok(());
+    }
+
+    let mut accepted = vec![];
+    for commit in commits {
+        if args.is_present("yes") || try!(confirm(args, &format!("Add \"{}\"? [y/N] ", commit.entry()))) {
+            accepted.push(commit.entry());
+        }
+    }
+    try!(app.record_all(Aspect::Yesterday, &accepted));
+    if json(args) {
+        print_json(ObjectBuilder::new()
+            .insert("added", accepted)
+            .insert("standup", jsonify::build_object(&app.get_standup()))
+            .unwrap());
+    } else {
+        println!("Added {} yesterday entries", accepted.len());
+    }
+    Ok(())
+}
+
+fn handle_post(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let date = args.value_of("date").map(|s| s.to_string());
+    let mut app = try!(App::new(profile, date));
+    app.set_author(args.value_of("as").map(|s| s.to_string()));
+    let chat = args.value_of("chat").and_then(chatify::Chat::from_name).unwrap_or(profile.chat);
+    let payload = chatify::serialize(&app.get_standup(), app.layout(), chat);
+    if args.is_present("dry_run") {
+        println!("{}", payload);
+        return Ok(());
+    }
+    let webhook = try!(args.value_of("webhook").map(|url| url.to_string()).or(profile.webhook.clone())
+                       .ok_or(not_set("post.webhook")));
+    try!(webhook::post(&webhook, &payload, 3, Duration::from_secs(2)));
+    if json(args) {
+        print_json(ObjectBuilder::new()
+            .insert("date", app.date().format("%F").to_string())
+            .insert("chat", chat.name())
+            .unwrap());
+    }
+    Ok(())
+}
+
+/// Templates in a `templates` directory next to the config file take the
+/// place of the built in ones.
+fn handle_publish(config: &Config, profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let app = try!(App::new(profile, None));
+    let out = args.value_of("out").unwrap();
+    let templates = config.file.parent().map(|dir| dir.join("templates"));
+    let site = publish::Site::new(app.standups(), app.layout(), publish::Templates::new(templates));
+    let pages = try!(site.publish(Path::new(out)));
+    if json(args) {
+        print_json(ObjectBuilder::new().insert("written", out).insert("pages", pages).unwrap());
+    } else {
+        println!("Published {} pages to {}", pages, out);
+    }
+    Ok(())
+}
+
+fn handle_serve(profile: &Profile, args: &ArgMatches) -> Result<(), CliError> {
+    let port = try!(parse_number(args.value_of("port"), 7878));
+    let app = try!(App::new(profile, None));
+    let server = try!(server::bind(&format!("127.0.0.1:{}", port)));
+    println!("Listening on http://{}", server.server_addr());
+    server::run(server, app);
+    Ok(())
+}
+
+fn not_set(key: &str) -> CliError {
+    CliError::Cli(StandupError::InvalidConfig(key.to_string(), "is not set".to_string()))
+}
+
+/// Whether `--output json` was given.
+fn json(args: &ArgMatches) -> bool {
+    args.value_of("output") == Some("json")
+}
+
+/// Prints what a command did, for `--output json`.
+fn print_json(value: Value) {
+    println!("{}", serde_json::to_string_pretty(&value).unwrap());
+}
+
+fn range_object(range: &(NaiveDate, NaiveDate)) -> Value {
+    ObjectBuilder::new()
+        .insert("from", range.0.format("%F").to_string())
+        .insert("to", range.1.format("%F").to_string())
+        .unwrap()
+}
+
+fn retention_object(retention: &Retention) -> Value {
+    ObjectBuilder::new()
+        .insert("keep_last", retention.keep_last)
+        .insert("daily", retention.daily)
+        .insert("weekly", retention.weekly)
+        .unwrap()
+}
+
+fn profile_object(name: &str, data_path: &Path, status: &str) -> Value {
+    ObjectBuilder::new()
+        .insert("name", name)
+        .insert("data", data_path.display().to_string())
+        .insert("status", status)
+        .unwrap()
+}
+
+/// Prints a standup after a change, as JSON with `--output json`.
+fn print_standup(args: &ArgMatches, app: &App, standup: &Standup) {
+    if json(args) {
+        print!("{}", jsonify::serialize_day(standup));
+    } else {
+        println!("{}", terminal(args).render_day(app.layout(), &[standup]));
+    }
+}
+
+/// How to print to stdout, following `--color`.
+fn terminal(args: &ArgMatches) -> Terminal {
+    Terminal::detect(args.value_of("color").and_then(termify::Color::from_name).unwrap_or(termify::Color::Auto))
+}
+
+/// Prints a listing, through the pager unless `--no-pager` is given.
+fn page(profile: &Profile, args: &ArgMatches, text: &str) -> Result<(), CliError> {
+    let command = if args.is_present("no_pager") {
+        None
+    } else {
+        pager::command(profile.pager.as_deref(), env::var("PAGER").ok())
+    };
+    pager::page(text, command)
+}
+
+/// Turns a line number as shown into an index.
+fn parse_line(value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(0) => Err(CliError::Cli(StandupError::LineNotFound)),
+        Ok(line) => Ok(line - 1),
+        Err(_) => Err(CliError::Cli(StandupError::InvalidNumber)),
+    }
+}
+
+fn parse_number(value: Option<&str>, default: usize) -> Result<usize, CliError> {
+    match value {
+        Some(value) => value.parse::<usize>().map_err(|_| CliError::Cli(StandupError::InvalidNumber)),
+        None => Ok(default),
+    }
+}
+
+/// Asks a yes or no question. With `--output json` the question goes to
+/// stderr so that stdout stays valid JSON.
+fn confirm(args: &ArgMatches, prompt: &str) -> Result<bool, CliError> {
+    if json(args) {
+        try!(write!(io::stderr(), "{}", prompt).map_err(CliError::Io));
+    } else {
+        print!("{}", prompt);
+        try!(io::stdout().flush().map_err(CliError::Io));
     }
+    let mut answer = String::new();
+    let stdin = io::stdin();
+    try!(stdin.lock().read_line(&mut answer).map_err(CliError::Io));
+    Ok(answer.trim().to_lowercase().starts_with("y"))
 }
@@ -0,0 +1,71 @@
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use crossterm::terminal;
+use ::CliError;
+
+/// The pager to use. `display.pager` comes first, where `off` turns paging
+/// off, then `$PAGER`, then `less -R` so that colours come through. An empty
+/// `$PAGER` also turns paging off.
+pub fn command(configured: Option<&str>, env: Option<String>) -> Option<String> {
+    match (configured, env) {
+        (Some("off"), _) => None,
+        (Some(configured), _) => Some(configured.to_string()),
+        (None, Some(env)) => if env.trim().is_empty() { None } else { Some(env) },
+        (None, None) => Some("less -R".to_string()),
+    }
+}
+
+/// Whether the text has more lines than fit on a screen of the given height.
+pub fn overflows(text: &str, height: usize) -> bool {
+    text.lines().count() > height
+}
+
+/// Prints the text, through the pager when stdout is a terminal and the text
+/// would scroll off the screen. When the pager cannot be started the text is
+/// printed as is.
+pub fn page(text: &str, command: Option<String>) -> Result<(), CliError> {
+    let print = || write!(io::stdout().lock(), "{}", text).map_err(CliError::Io);
+    let height = terminal::size().ok()
+        .and_then(|(_, rows)| if rows > 0 { Some(rows as usize) } else { None })
+        .unwrap_or(usize::max_value());
+    let command = match command {
+        Some(ref command) if io::stdout().is_terminal() && overflows(text, height) => command.clone(),
+        _ => return print(),
+    };
+    let mut words = command.split_whitespace();
+    let child = words.next().map(|program| Command::new(program).args(words).stdin(Stdio::piped()).spawn());
+    let mut child = match child {
+        Some(Ok(child)) => child,
+        _ => return print(),
+    };
+    if let Some(mut input) = child.stdin.take() {
+        // Quitting the pager before the end closes the pipe, which is fine.
+        if let Err(err) = input.write_all(text.as_bytes()) {
+            if err.kind() != io::ErrorKind::BrokenPipe {
+                return Err(CliError::Io(err));
+            }
+        }
+    }
+    try!(child.wait().map_err(CliError::Io));
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_will_pick_the_pager() {
+        assert_eq!(command(None, None), Some("less -R".to_string()));
+        assert_eq!(command(None, Some("more".to_string())), Some("more".to_string()));
+        assert_eq!(command(None, Some("".to_string())), None);
+        assert_eq!(command(Some("most"), Some("more".to_string())), Some("most".to_string()));
+        assert_eq!(command(Some("off"), Some("more".to_string())), None);
+    }
+
+    #[test]
+    fn it_will_only_page_what_does_not_fit() {
+        assert!(!overflows("one\ntwo\n", 2));
+        assert!(overflows("one\ntwo\nthree\n", 2));
+    }
+}
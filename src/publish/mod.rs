@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::io::{Read, Write};
+use std::collections::BTreeMap;
+use chrono::*;
+use ::CliError;
+use standup::{Standup, Aspect, Layout, tag_of};
+
+/// The templates built into the binary. A file with the same name in the
+/// templates directory takes the place of any of them.
+static TEMPLATES: &'static [(&'static str, &'static str)] = &[
+    ("page.html", include_str!("templates/page.html")),
+    ("index.html", include_str!("templates/index.html")),
+    ("day.html", include_str!("templates/day.html")),
+    ("week.html", include_str!("templates/week.html")),
+    ("tag.html", include_str!("templates/tag.html")),
+    ("style.css", include_str!("templates/style.css")),
+];
+
+pub struct Templates {
+    dir: Option<PathBuf>,
+}
+
+impl Templates {
+    /// Uses the templates in the directory where there are any, and the
+    /// built in ones otherwise.
+    pub fn new(dir: Option<PathBuf>) -> Templates {
+        Templates { dir: dir }
+    }
+
+    fn get(&self, name: &str) -> Result<String, CliError> {
+        if let Some(path) = self.dir.as_ref().map(|dir| dir.join(name)).filter(|path| path.is_file()) {
+            let mut buf = String::new();
+            try!(fs::File::open(path).and_then(|mut file| file.read_to_string(&mut buf)).map_err(CliError::Io));
+            return Ok(buf);
+        }
+        Ok(TEMPLATES.iter().find(|&&(known, _)| known == name).map(|&(_, template)| template.to_string()).unwrap())
+    }
+}
+
+///
+/// ## Site
+///
+/// A static HTML archive of the standups: an index with a heatmap of
+/// activity, and a page for every day, week and tag. Pages link to each other
+/// relatively, so the site can be opened straight from disk.
+///
+pub struct Site<'a> {
+    standups: Vec<&'a Standup>,
+    layout: &'a Layout,
+    templates: Templates,
+}
+
+impl<'a> Site<'a> {
+    pub fn new(standups: Vec<&'a Standup>, layout: &'a Layout, templates: Templates) -> Site<'a> {
+        Site { standups: standups, layout: layout, templates: templates }
+    }
+
+    /// Writes the site into the directory, returning how many pages it has.
+    pub fn publish(&self, out: &Path) -> Result<usize, CliError> {
+        for dir in &["days", "weeks", "tags"] {
+            try!(fs::create_dir_all(out.join(dir)).map_err(CliError::Io));
+        }
+        try!(write(&out.join("style.css"), &try!(self.templates.get("style.css"))));
+
+        let days = self.days();
+        let mut weeks: BTreeMap<String, Vec<&Vec<&Standup>>> = BTreeMap::new();
+        for day in days.iter() {
+            weeks.entry(week_of(&day[0].date)).or_insert(vec![]).push(day);
+        }
+        let mut tags: BTreeMap<String, Vec<&Standup>> = BTreeMap::new();
+        for standup in self.standups.iter() {
+            for tag in standup.tags() {
+                tags.entry(tag).or_insert(vec![]).push(standup);
+            }
+        }
+
+        let mut pages = 0;
+        for day in days.iter() {
+            let date = day[0].date.format("%F").to_string();
+            let content = fill(&try!(self.templates.get("day.html")), &[
+                ("root", "../".to_string()),
+                ("date", escape(&day[0].date.format(&self.layout.date_format).to_string())),
+                ("week", week_of(&day[0].date)),
+                ("standups", day.iter().map(|standup| self.render_standup(standup, "../")).collect()),
+            ]);
+            try!(self.write_page(out, &format!("days/{}.html", date), &date, "../", content));
+            pages += 1;
+        }
+        for (week, days) in weeks.iter() {
+            let content = fill(&try!(self.templates.get("week.html")), &[
+                ("root", "../".to_string()),
+                ("week", week.clone()),
+                ("days", days.iter().map(|day| self.render_day(day, "../")).collect()),
+            ]);
+            try!(self.write_page(out, &format!("weeks/{}.html", week), week, "../", content));
+            pages += 1;
+        }
+        for (tag, standups) in tags.iter() {
+            let content = fill(&try!(self.templates.get("tag.html")), &[
+                ("root", "../".to_string()),
+                ("tag", escape(tag)),
+                ("entries", standups.iter().map(|standup| self.render_tagged(standup, tag)).collect()),
+            ]);
+            try!(self.write_page(out, &format!("tags/{}.html", tag), &format!("#{}", tag), "../", content));
+            pages += 1;
+        }
+
+        let content = fill(&try!(self.templates.get("index.html")), &[
+            ("root", String::new()),
+            ("heatmap", heatmap(&days)),
+            ("weeks", weeks.iter().rev().map(|(week, days)| {
+                format!("<li><a href=\"weeks/{0}.html\">{0}</a> ({1} days)</li>\n", week, days.len())
+            }).collect()),
+            ("tags", tags.iter().map(|(tag, standups)| {
+                format!("<li><a href=\"tags/{0}.html\">#{0}</a> ({1})</li>\n", escape(tag), standups.len())
+            }).collect()),
+        ]);
+        try!(self.write_page(out, "index.html", "Standups", "", content));
+        Ok(pages + 1)
+    }
+
+    /// The standups grouped by day, oldest first.
+    fn days(&self) -> Vec<Vec<&'a Standup>> {
+        let mut days: Vec<Vec<&Standup>> = vec![];
+        for standup in self.standups.iter() {
+            if days.last().map_or(false, |day| day[0].date == standup.date) {
+                days.last_mut().unwrap().push(standup);
+            } else {
+                days.push(vec![standup]);
+            }
+        }
+        days
+    }
+
+    fn write_page(&self, out: &Path, path: &str, title: &str, root: &str, content: String) -> Result<(), CliError> {
+        let page = fill(&try!(self.templates.get("page.html")), &[
+            ("root", root.to_string()),
+            ("title", escape(title)),
+            ("content", content),
+        ]);
+        write(&out.join(path), &page)
+    }
+
+    fn render_day(&self, day: &[&Standup], root: &str) -> String {
+        let date = day[0].date;
+        let mut out = format!("<h2><a href=\"{}days/{}.html\">{}</a></h2>\n",
+                              root, date.format("%F"), escape(&date.format(&self.layout.date_format).to_string()));
+        for standup in day {
+            out.push_str(&self.render_standup(standup, root));
+        }
+        out
+    }
+
+    fn render_standup(&self, standup: &Standup, root: &str) -> String {
+        let class = if standup.is_blocked() { "standup blocked" } else { "standup" };
+        let mut out = format!("<article class=\"{}\">\n", class);
+        if let Some(ref author) = standup.author {
+            out.push_str(&format!("<h3>{}</h3>\n", escape(author)));
+        }
+        for &aspect in self.layout.order.iter() {
+            let messages = standup.messages(aspect);
+            if messages.is_empty() && self.layout.hide_empty.contains(&aspect) {
+                continue;
+            }
+            out.push_str(&format!("<h4>{}</h4>\n<ol>\n", aspect));
+            for message in messages.iter() {
+                out.push_str(&format!("<li{}>{}</li>\n", entry_class(aspect), link_tags(message, root)));
+            }
+            out.push_str("</ol>\n");
+        }
+        out.push_str("</article>\n");
+        out
+    }
+
+    /// The entries in the standup that use the tag.
+    fn render_tagged(&self, standup: &Standup, tag: &str) -> String {
+        let mut out = String::new();
+        let who = standup.author.as_ref().map_or(String::new(), |author| format!(" {}", escape(author)));
+        for aspect in Aspect::all() {
+            for message in standup.messages(aspect).iter() {
+                if message.split_whitespace().any(|word| tag_of(word).as_ref().map(|t| t.as_str()) == Some(tag)) {
+                    out.push_str(&format!("<li{}><a href=\"../days/{1}.html\">{1}</a>{2} {3}: {4}</li>\n",
+                                          entry_class(aspect), standup.date.format("%F"), who, aspect,
+                                          link_tags(message, "../")));
+                }
+            }
+        }
+        out
+    }
+}
+
+/// Weeks are named by their ISO week, such as `2016-W09`.
+fn week_of(date: &NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+fn entry_class(aspect: Aspect) -> &'static str {
+    if aspect == Aspect::Blocker { " class=\"blocker\"" } else { "" }
+}
+
+/// A table with a column per week and a row per weekday, shaded by how many
+/// entries were made that day. Days with blockers are outlined.
+fn heatmap(days: &[Vec<&Standup>]) -> String {
+    let (first, last) = match (days.first(), days.last()) {
+        (Some(first), Some(last)) => (first[0].date, last[0].date),
+        _ => return String::new(),
+    };
+    let mut activity = BTreeMap::new();
+    for day in days {
+        let entries: usize = day.iter()
+            .map(|standup| Aspect::all().into_iter().map(|aspect| standup.messages(aspect).len()).sum::<usize>())
+            .sum();
+        let blocked = day.iter().any(|standup| standup.is_blocked());
+        activity.insert(day[0].date.format("%F").to_string(), (entries, blocked));
+    }
+
+    let start = first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    let weeks = (last - start).num_days() / 7 + 1;
+    let mut out = String::from("<table>\n");
+    for (row, name) in ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"].iter().enumerate() {
+        out.push_str(&format!("<tr><th>{}</th>", name));
+        for week in 0..weeks {
+            let date = start + Duration::days(week * 7 + row as i64);
+            let key = date.format("%F").to_string();
+            match activity.get(&key) {
+                Some(&(entries, blocked)) => {
+                    out.push_str(&format!("<td class=\"l{}{}\"><a href=\"days/{2}.html\" title=\"{2}: {3} entries\"></a></td>",
+                                          level(entries), if blocked { " blocked" } else { "" }, key, entries));
+                },
+                None => out.push_str("<td></td>"),
+            }
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>");
+    out
+}
+
+/// How dark a day is on the heatmap, from 0 to 4.
+fn level(entries: usize) -> usize {
+    match entries {
+        0 => 0,
+        1..=2 => 1,
+        3..=4 => 2,
+        5..=7 => 3,
+        _ => 4,
+    }
+}
+
+/// Escapes the message, turning each tag into a link to its page.
+fn link_tags(message: &str, root: &str) -> String {
+    message.split(' ').map(|word| {
+        match tag_of(word) {
+            Some(tag) => {
+                let end = word[1..].find(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+                    .map_or(word.len(), |at| at + 1);
+                format!("<a href=\"{}tags/{}.html\">{}</a>{}", root, escape(&tag), escape(&word[..end]), escape(&word[end..]))
+            },
+            None => escape(word),
+        }
+    }).collect::<Vec<String>>().join(" ")
+}
+
+/// Puts values in place of the `{{name}}`s in a template. Values are not
+/// looked at again, so an entry that happens to contain braces is left alone.
+fn fill(template: &str, values: &[(&str, String)]) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let name = after[..end].trim();
+                match values.iter().find(|&&(key, _)| key == name) {
+                    Some(&(_, ref value)) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            },
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace("&", "&amp;")
+        .replace("<", "&lt;")
+        .replace(">", "&gt;")
+        .replace("\"", "&quot;")
+        .replace("'", "&#39;")
+}
+
+fn write(path: &Path, contents: &str) -> Result<(), CliError> {
+    fs::File::create(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(CliError::Io)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+    use std::io::Read;
+    use standup::{Standup, Aspect, Layout};
+    use store::test::scratch;
+
+    fn read(path: &Path) -> String {
+        let mut buf = String::new();
+        fs::File::open(path).unwrap().read_to_string(&mut buf).unwrap();
+        buf
+    }
+
+    fn standups() -> Vec<Standup> {
+        vec![
+            Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap()).add(Aspect::Today, "cut the #release"),
+            Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())
+                .add(Aspect::Yesterday, "cut the #release")
+                .add(Aspect::Blocker, "waiting on <review>"),
+        ]
+    }
+
+    #[test]
+    fn it_will_write_a_page_per_day_week_and_tag() {
+        let out = scratch("publish-site");
+        let standups = standups();
+        let layout = Layout::default();
+        let site = Site::new(standups.iter().collect(), &layout, Templates::new(None));
+        assert_eq!(site.publish(&out).unwrap(), 6);
+
+        let index = read(&out.join("index.html"));
+        assert!(index.contains("<td class=\"l1\"><a href=\"days/2016-03-04.html\""));
+        assert!(index.contains("<td class=\"l1 blocked\"><a href=\"days/2016-03-07.html\""));
+        assert!(index.contains("href=\"tags/release.html\""));
+        let day = read(&out.join("days").join("2016-03-07.html"));
+        assert!(day.contains("<li class=\"blocker\">waiting on &lt;review&gt;</li>"));
+        assert!(day.contains("<a href=\"../tags/release.html\">#release</a>"));
+        assert!(out.join("weeks").join("2016-W09.html").is_file());
+        assert!(out.join("weeks").join("2016-W10.html").is_file());
+        assert_eq!(read(&out.join("tags").join("release.html")).matches("</li>").count(), 2);
+    }
+
+    #[test]
+    fn it_will_prefer_templates_from_the_directory() {
+        let dir = scratch("publish-templates");
+        let out = dir.join("site");
+        fs::File::create(dir.join("day.html")).unwrap().write_all(b"<p>Custom {{date}}</p>").unwrap();
+        let standups = standups();
+        let layout = Layout::default();
+        Site::new(standups.iter().collect(), &layout, Templates::new(Some(dir))).publish(&out).unwrap();
+        assert!(read(&out.join("days").join("2016-03-04.html")).contains("<p>Custom 2016-03-04 - Friday</p>"));
+        assert!(read(&out.join("index.html")).contains("<h1>Standups</h1>"));
+    }
+
+    #[test]
+    fn it_will_only_fill_in_the_template() {
+        let values = [("title", "{{content}}".to_string()), ("content", "body".to_string())];
+        assert_eq!(fill("<h1>{{title}}</h1>{{ content }}{{other}}{{", &values),
+                   "<h1>{{content}}</h1>body{{other}}{{");
+    }
+}
@@ -0,0 +1,300 @@
+use std::str;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use chrono::*;
+use serde_json::{from_str, Value};
+use serde_json::builder::ObjectBuilder;
+use tiny_http::{Server, Request, Response, Header};
+use ::CliError;
+use ::StandupError;
+use app::App;
+use standup::Aspect;
+use jsonify;
+
+/// Starts listening on the address, such as `127.0.0.1:7878`. A port of 0
+/// picks a free one.
+pub fn bind(addr: &str) -> Result<Server, CliError> {
+    Server::http(addr).map_err(|err| CliError::Http(format!("could not start the server: {}", err)))
+}
+
+///
+/// ## Server
+///
+/// Serves the standups as JSON until the process is stopped:
+///
+/// - `GET /standups?from=DATE&to=DATE&as=NAME` lists standups
+/// - `GET /standups/DATE` returns everyone's standup for the day
+/// - `POST /standups/DATE/ASPECT` adds `{"text": "..."}` as an entry
+/// - `PUT /standups/DATE/ASPECT/LINE` replaces an entry's text
+/// - `DELETE /standups/DATE/ASPECT/LINE` removes an entry
+/// - `DELETE /standups/DATE` removes the day's standup
+///
+/// Changes are made as `team.identity`, or as `?as=NAME`. Each request is
+/// handled on its own thread, with the app behind a lock so that writes
+/// happen one at a time.
+///
+pub fn run(server: Server, app: App) {
+    let app = Arc::new(Mutex::new(app));
+    for request in server.incoming_requests() {
+        let app = app.clone();
+        thread::spawn(move || respond(request, &app));
+    }
+}
+
+fn respond(mut request: Request, app: &Mutex<App>) {
+    let mut body = String::new();
+    let (status, value) = match request.as_reader().read_to_string(&mut body) {
+        Ok(_) => {
+            let mut app = app.lock().unwrap();
+            handle(&mut app, request.method().as_str(), request.url(), &body)
+        },
+        Err(_) => error(400, "the request body must be UTF-8"),
+    };
+    let header = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+    let response = Response::from_string(format!("{}", value))
+        .with_status_code(status)
+        .with_header(header);
+    let _ = request.respond(response);
+}
+
+/// Routes a request to the app, returning the status and the JSON body.
+fn handle(app: &mut App, method: &str, url: &str, body: &str) -> (u16, Value) {
+    let (path, query) = match url.find('?') {
+        Some(at) => (&url[..at], parse_query(&url[at + 1..])),
+        None => (url, vec![]),
+    };
+    let param = |name: &str| query.iter().find(|&&(ref key, _)| key == name).map(|&(_, ref value)| value.clone());
+
+    if let Err(err) = app.reload() {
+        return from_error(err);
+    }
+    app.set_author(param("as"));
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    if segments[0] != "standups" {
+        return error(404, "there is nothing here, try /standups");
+    }
+    let result = match (method, &segments[1..]) {
+        ("GET", []) => list(app, param("from"), param("to"), param("as")),
+        ("GET", [date]) => with_date(app, date).and_then(|_| day(app)),
+        ("DELETE", [date]) => with_date(app, date).and_then(|_| delete(app)),
+        ("POST", [date, aspect]) => {
+            with_date(app, date)
+                .and_then(|_| parse_aspect(aspect))
+                .and_then(|aspect| parse_text(body).map(|text| (aspect, text)))
+                .and_then(|(aspect, text)| {
+                    app.record(aspect, text).map_err(from_error)
+                })
+                .map(|_| (201, jsonify::build_object(&app.get_standup())))
+        },
+        ("PUT", [date, aspect, line]) => {
+            with_date(app, date)
+                .and_then(|_| parse_aspect(aspect))
+                .and_then(|aspect| parse_line(line).map(|index| (aspect, index)))
+                .and_then(|(aspect, index)| parse_text(body).map(|text| (aspect, index, text)))
+                .and_then(|(aspect, index, text)| {
+                    app.edit_line(aspect, index, text).map_err(from_error)
+                })
+                .map(|_| (200, jsonify::build_object(&app.get_standup())))
+        },
+        ("DELETE", [date, aspect, line]) => {
+            with_date(app, date)
+                .and_then(|_| parse_aspect(aspect))
+                .and_then(|aspect| parse_line(line).map(|index| (aspect, index)))
+                .and_then(|(aspect, index)| app.delete_line(aspect, index).map_err(from_error))
+                .map(|_| (200, jsonify::build_object(&app.get_standup())))
+        },
+        (_, []) | (_, [_]) | (_, [_, _]) | (_, [_, _, _]) => Err(error(405, "that method is not allowed here")),
+        _ => Err(error(404, "there is nothing here, try /standups")),
+    };
+    match result {
+        Ok(reply) | Err(reply) => reply,
+    }
+}
+
+fn list(app: &App, from: Option<String>, to: Option<String>, author: Option<String>) -> Result<(u16, Value), (u16, Value)> {
+    let from = try!(parse_bound(from));
+    let to = try!(parse_bound(to));
+    let standups = app.standups_between(from, to).into_iter()
+        .filter(|standup| author.is_none() || standup.author == author)
+        .map(|standup| jsonify::build_object(standup))
+        .collect();
+    Ok((200, Value::Array(standups)))
+}
+
+fn day(app: &App) -> Result<(u16, Value), (u16, Value)> {
+    let standups: Vec<Value> = app.manager().day(&app.get_standup().date).into_iter()
+        .map(|standup| jsonify::build_object(standup))
+        .collect();
+    if standups.is_empty() {
+        Err(error(404, "nobody has posted a standup on that day"))
+    } else {
+        Ok((200, Value::Array(standups)))
+    }
+}
+
+fn delete(app: &mut App) -> Result<(u16, Value), (u16, Value)> {
+    match try!(app.delete().map_err(from_error)) {
+        Some(standup) => Ok((200, jsonify::build_object(&standup))),
+        None => Err(error(404, "there is no standup on that day")),
+    }
+}
+
+fn with_date(app: &mut App, date: &str) -> Result<(), (u16, Value)> {
+    App::parse_date(date)
+        .map(|date| app.set_date(date))
+        .map_err(from_error)
+}
+
+fn parse_bound(date: Option<String>) -> Result<Option<NaiveDate>, (u16, Value)> {
+    match date {
+        Some(date) => App::parse_date(&date).map(Some).map_err(from_error),
+        None => Ok(None),
+    }
+}
+
+fn parse_aspect(name: &str) -> Result<Aspect, (u16, Value)> {
+    Aspect::from_name(name).ok_or(error(400, "the aspect must be today, yesterday or blocker"))
+}
+
+/// Lines are numbered from 1, as they are when shown.
+fn parse_line(line: &str) -> Result<usize, (u16, Value)> {
+    match line.parse::<usize>() {
+        Ok(line) if line > 0 => Ok(line - 1),
+        _ => Err(error(400, "the line must be a number from 1")),
+    }
+}
+
+fn parse_text(body: &str) -> Result<String, (u16, Value)> {
+    from_str::<Value>(body).ok()
+        .and_then(|value| value.find("text").and_then(|text| text.as_string()).map(|text| text.to_string()))
+        .ok_or(error(400, "the body must be JSON like {\"text\": \"...\"}"))
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            let key = decode(parts.next().unwrap_or(""));
+            let value = decode(parts.next().unwrap_or(""));
+            (key, value)
+        })
+        .collect()
+}
+
+/// Undoes the percent encoding of a query string, leaving anything that is
+/// not a valid escape alone.
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    },
+                    None => out.push(b'%'),
+                }
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn from_error(err: CliError) -> (u16, Value) {
+    let status = match err {
+        CliError::Cli(StandupError::InvalidDate) => 400,
+        CliError::Cli(StandupError::LineNotFound) => 404,
+        _ => 500,
+    };
+    error(status, &err.to_string())
+}
+
+fn error(status: u16, message: &str) -> (u16, Value) {
+    (status, ObjectBuilder::new().insert("error", message).unwrap())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpStream, SocketAddr};
+    use std::thread;
+    use app::test::app;
+
+    fn start(name: &str) -> SocketAddr {
+        let server = bind("127.0.0.1:0").unwrap();
+        let addr = server.server_addr().to_ip().unwrap();
+        let app = app(name, "2016-03-04");
+        thread::spawn(move || run(server, app));
+        addr
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(stream, "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+               method, path, body.len(), body).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = &response[response.find("\r\n\r\n").unwrap() + 4..];
+        (status, from_str(body).unwrap())
+    }
+
+    #[test]
+    fn it_can_add_edit_and_delete_entries() {
+        let addr = start("server-entries");
+        let (status, standup) = request(addr, "POST", "/standups/2016-03-04/today", "{\"text\": \"review\"}");
+        assert_eq!(status, 201);
+        assert_eq!(standup.find("today").unwrap().as_array().unwrap().len(), 1);
+
+        let (status, standup) = request(addr, "PUT", "/standups/2016-03-04/today/1", "{\"text\": \"deploy\"}");
+        assert_eq!(status, 200);
+        assert_eq!(standup.find("today").unwrap().as_array().unwrap()[0].as_string(), Some("deploy"));
+
+        assert_eq!(request(addr, "PUT", "/standups/2016-03-04/today/2", "{\"text\": \"x\"}").0, 404);
+        assert_eq!(request(addr, "DELETE", "/standups/2016-03-04/today/1", "").0, 200);
+        assert_eq!(request(addr, "DELETE", "/standups/2016-03-04", "").0, 200);
+        assert_eq!(request(addr, "GET", "/standups/2016-03-04", "").0, 404);
+    }
+
+    #[test]
+    fn it_can_list_and_filter_standups() {
+        let addr = start("server-list");
+        request(addr, "POST", "/standups/2016-03-03/today?as=alice", "{\"text\": \"review\"}");
+        request(addr, "POST", "/standups/2016-03-04/today?as=bob", "{\"text\": \"deploy\"}");
+        request(addr, "POST", "/standups/2016-03-04/blocker?as=alice", "{\"text\": \"waiting\"}");
+
+        let (status, standups) = request(addr, "GET", "/standups?from=2016-03-04", "");
+        assert_eq!(status, 200);
+        assert_eq!(standups.as_array().unwrap().len(), 2);
+        let (_, standups) = request(addr, "GET", "/standups?as=alice", "");
+        assert_eq!(standups.as_array().unwrap().len(), 2);
+        let (_, day) = request(addr, "GET", "/standups/2016-03-04", "");
+        assert_eq!(day.as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_will_reject_bad_requests() {
+        let addr = start("server-errors");
+        assert_eq!(request(addr, "GET", "/standups/yesterday", "").0, 400);
+        assert_eq!(request(addr, "POST", "/standups/2016-03-04/lunch", "{\"text\": \"x\"}").0, 400);
+        assert_eq!(request(addr, "POST", "/standups/2016-03-04/today", "review").0, 400);
+        assert_eq!(request(addr, "PATCH", "/standups", "").0, 405);
+        assert_eq!(request(addr, "GET", "/nope", "").0, 404);
+    }
+
+    #[test]
+    fn it_will_decode_query_values() {
+        assert_eq!(decode("jane%20doe"), "jane doe");
+        assert_eq!(decode("a+b"), "a b");
+        assert_eq!(decode("100%"), "100%");
+    }
+}
@@ -0,0 +1,104 @@
+use standup::{Standup, Aspect};
+
+///
+/// ## Layout
+///
+/// How a standup is printed: the format of the date heading, the order the
+/// aspects come in and which of them are left out when they have no entries.
+///
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Layout {
+    pub date_format: String,
+    pub order: Vec<Aspect>,
+    pub hide_empty: Vec<Aspect>,
+}
+
+impl Layout {
+    pub fn default() -> Layout {
+        Layout {
+            date_format: "%F - %A".to_string(),
+            order: Aspect::all(),
+            hide_empty: vec![Aspect::Blocker],
+        }
+    }
+
+    pub fn render(&self, standup: &Standup) -> String {
+        let mut out = format!("{}\n", standup.date.format(&self.date_format));
+        out.push_str(&self.render_aspects(standup, "  "));
+        out
+    }
+
+    /// Renders everyone's standups for a day under a single heading, grouped
+    /// by person. A day with only an unattributed standup renders just like
+    /// `render`.
+    pub fn render_day(&self, standups: &[&Standup]) -> String {
+        match standups.first() {
+            None => String::new(),
+            Some(first) if standups.len() == 1 && first.author.is_none() => self.render(first),
+            Some(first) => {
+                let mut out = format!("{}\n", first.date.format(&self.date_format));
+                for standup in standups {
+                    let author = standup.author.as_ref().map_or("(no author)", |author| author.as_str());
+                    out.push_str(&format!("  {}:\n", author));
+                    out.push_str(&self.render_aspects(standup, "    "));
+                }
+                out
+            },
+        }
+    }
+
+    fn render_aspects(&self, standup: &Standup, indent: &str) -> String {
+        let mut out = String::new();
+        for &aspect in self.order.iter() {
+            let messages = standup.messages(aspect);
+            if messages.is_empty() && self.hide_empty.contains(&aspect) {
+                continue;
+            }
+            out.push_str(&format!("{}{}:\n", indent, aspect));
+            for (i, message) in messages.iter().enumerate() {
+                out.push_str(&format!("{}  {}. {}\n", indent, i + 1, message));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use standup::{Standup, Aspect};
+    use chrono::*;
+
+    #[test]
+    fn it_will_hide_empty_blockers_by_default() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap()).add(Aspect::Today, "today");
+        assert_eq!(Layout::default().render(&standup),
+                   "2016-03-07 - Monday\n  today:\n    1. today\n  yesterday:\n");
+    }
+
+    #[test]
+    fn it_will_group_a_day_by_person() {
+        let alice = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())
+            .set_author(Some("alice".to_string()))
+            .add(Aspect::Today, "review");
+        let bob = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())
+            .set_author(Some("bob".to_string()))
+            .add(Aspect::Blocker, "waiting");
+        assert_eq!(Layout::default().render_day(&[&alice, &bob]), concat!(
+            "2016-03-07 - Monday\n",
+            "  alice:\n    today:\n      1. review\n    yesterday:\n",
+            "  bob:\n    today:\n    yesterday:\n    blocker:\n      1. waiting\n"));
+    }
+
+    #[test]
+    fn it_can_reorder_and_hide_aspects() {
+        let layout = Layout {
+            date_format: "%d/%m".to_string(),
+            order: vec![Aspect::Blocker, Aspect::Yesterday, Aspect::Today],
+            hide_empty: vec![Aspect::Today],
+        };
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap()).add(Aspect::Yesterday, "done");
+        assert_eq!(layout.render(&standup),
+                   "07/03\n  blocker:\n  yesterday:\n    1. done\n");
+    }
+}
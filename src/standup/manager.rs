@@ -1,93 +1,249 @@
-use standup::Standup;
+use standup::{Standup, Aspect};
 use std::io::{Read,Write};
-use chrono::Date;
-use chrono::offset::local::Local;
+use chrono::NaiveDate;
 use std::collections::BTreeMap;
 use jsonify;
+use jsonify::Style;
 use ::CliError;
 
+/// Standups are kept by day and then by person. A standup without an author
+/// sorts first on its day.
+type Key = (NaiveDate, Option<String>);
+
+#[derive(Clone)]
 pub struct Manager {
-    standups: BTreeMap<Date<Local>, Standup>
+    standups: BTreeMap<Key, Standup>,
+    style: Style,
+    changes: Vec<Change>,
+}
+
+/// A single mutation of the manager. These are what gets appended to the
+/// journal, and replaying them in order rebuilds the same state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Change {
+    Put(Standup),
+    Delete(NaiveDate, Option<String>),
+}
+
+/// A day and aspect where both sides of a merge added different entries.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    pub date: NaiveDate,
+    pub aspect: Aspect,
 }
 
 impl Manager {
     pub fn new() -> Manager {
-        Manager { standups: BTreeMap::new() }
+        Manager { standups: BTreeMap::new(), style: Style::Compact, changes: vec![] }
     }
 
+    /// Loads the standups from JSON in any of the styles. The style is kept so
+    /// that flushing writes the file back the way it was found.
     pub fn from_reader<F: Read>(mut reader: F) -> Result<Manager, CliError> {
         let mut buf = String::new();
         try!(reader.read_to_string(&mut buf).map_err(CliError::Io));
 
-        let mut manager = Manager { standups: BTreeMap::new() };
-        for standup in jsonify::deserialize(buf).unwrap() {
-            manager.insert(standup);
+        let style = if buf.trim().is_empty() { Style::Compact } else { Style::detect(&buf) };
+        let mut manager = Manager { standups: BTreeMap::new(), style: style, changes: vec![] };
+        for standup in try!(jsonify::deserialize(buf).map_err(CliError::Parse)) {
+            manager.apply(Change::Put(standup));
         }
         Ok(manager)
     }
 
     pub fn flush<F: Write>(&self, mut writer: F) -> Result<(), CliError> {
         let standups: Vec<&Standup> = self.standups.values().clone().collect();
-        writer.write(jsonify::serialize(&standups).as_bytes())
-            .map(|_| ())
+        writer.write_all(jsonify::serialize_with(&standups, self.style).as_bytes())
             .map_err(CliError::Io)
     }
 
+    pub fn style(&self) -> Style {
+        self.style
+    }
+
+    pub fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
     pub fn standups(&self) -> Vec<&Standup> {
         self.standups.values().collect()
     }
 
-    pub fn get(&self, date: &Date<Local>) -> Option<Standup> {
-        self.standups.get(&date).map(|standup| standup.clone())
+    pub fn get_by(&self, date: &NaiveDate, author: Option<&str>) -> Option<Standup> {
+        self.standups.get(&(date.clone(), author.map(|a| a.to_string()))).map(|standup| standup.clone())
+    }
+
+    /// Every standup on the day, whoever wrote it.
+    pub fn day(&self, date: &NaiveDate) -> Vec<&Standup> {
+        self.standups.values().filter(|standup| standup.date == *date).collect()
     }
 
     pub fn insert(&mut self, standup: Standup) {
-        self.standups.insert(standup.date.clone(), standup);
+        self.changes.push(Change::Put(standup.clone()));
+        self.apply(Change::Put(standup));
     }
 
-    pub fn delete(&mut self, date: &Date<Local>) -> Option<Standup> {
-        self.standups.remove(date)
+    pub fn delete_by(&mut self, date: &NaiveDate, author: Option<&str>) -> Option<Standup> {
+        let change = Change::Delete(date.clone(), author.map(|a| a.to_string()));
+        self.changes.push(change.clone());
+        self.apply(change)
+    }
+
+    /// Applies a change without recording it, for replaying a journal.
+    pub fn apply(&mut self, change: Change) -> Option<Standup> {
+        match change {
+            Change::Put(standup) => {
+                let key = (standup.date.clone(), standup.author.clone());
+                self.standups.insert(key, standup)
+            },
+            Change::Delete(date, author) => self.standups.remove(&(date, author)),
+        }
+    }
+
+    /// Hands over the changes made since the last call, oldest first.
+    pub fn take_changes(&mut self) -> Vec<Change> {
+        self.changes.drain(..).collect()
+    }
+
+    /// Merges every standup from the other manager into this one, day by day
+    /// and aspect by aspect. Returns the places where both sides diverged.
+    pub fn merge(&mut self, other: Manager) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        for (key, theirs) in other.standups {
+            let merged = match self.standups.get(&key).cloned() {
+                Some(ours) => {
+                    for aspect in ours.conflicts(&theirs) {
+                        conflicts.push(Conflict { date: key.0.clone(), aspect: aspect });
+                    }
+                    ours.merge(&theirs)
+                },
+                None => theirs
+            };
+            self.insert(merged);
+        }
+        conflicts
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use chrono::*;
-    use standup::Standup;
+    use standup::{Standup, Aspect};
+    use jsonify::Style;
     use std::str;
 
     #[test]
     fn it_can_read_the_standups_out_of_a_stream() {
         let manager = Manager::from_reader("[{\"date\":\"2015-01-01\"}]".as_bytes()).unwrap();
-        assert_eq!(manager.get(&Local.ymd(2015,1,1)).is_some(), true);
+        assert_eq!(manager.get_by(&NaiveDate::from_ymd_opt(2015,1,1).unwrap(), None).is_some(), true);
     }
 
     #[test]
     fn it_can_flush_to_a_stream() {
         let mut manager = Manager::from_reader("[]".as_bytes()).unwrap();
-        manager.insert(Standup::from_date(Local.ymd(2015, 1, 1)));
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()));
         let mut bytes: Vec<u8> = Vec::new();
         manager.flush(&mut bytes).unwrap();
         let json = str::from_utf8(bytes.as_slice()).unwrap();
         assert_eq!(json.contains("\"date\":\"2015-01-01\""), true);
     }
 
+    #[test]
+    fn it_will_flush_in_the_style_it_was_read_in() {
+        let json = "{\"date\":\"2015-01-01\"}\n{\"date\":\"2015-01-02\"}\n";
+        let manager = Manager::from_reader(json.as_bytes()).unwrap();
+        assert_eq!(manager.style(), Style::Lines);
+        let mut bytes: Vec<u8> = Vec::new();
+        manager.flush(&mut bytes).unwrap();
+        assert_eq!(str::from_utf8(bytes.as_slice()).unwrap().lines().count(), 2);
+    }
+
+    #[test]
+    fn it_can_change_the_style() {
+        let mut manager = Manager::from_reader("[{\"date\":\"2015-01-01\"}]".as_bytes()).unwrap();
+        manager.set_style(Style::Pretty);
+        let mut bytes: Vec<u8> = Vec::new();
+        manager.flush(&mut bytes).unwrap();
+        assert!(str::from_utf8(bytes.as_slice()).unwrap().contains("\n  {\n"));
+    }
+
+    #[test]
+    fn it_will_fail_on_invalid_json() {
+        assert!(Manager::from_reader("[{".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn it_will_record_changes() {
+        let mut manager = Manager::from_reader("[{\"date\":\"2015-01-01\"}]".as_bytes()).unwrap();
+        assert_eq!(manager.take_changes().len(), 0);
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 2).unwrap());
+        manager.insert(standup.clone());
+        manager.delete_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None);
+        assert_eq!(manager.take_changes(),
+                   vec![Change::Put(standup), Change::Delete(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None)]);
+        assert_eq!(manager.take_changes().len(), 0);
+    }
+
+    #[test]
+    fn it_can_apply_changes_without_recording_them() {
+        let mut manager = Manager::new();
+        manager.apply(Change::Put(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap())));
+        assert!(manager.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).is_some());
+        assert_eq!(manager.take_changes().len(), 0);
+    }
+
     #[test]
     fn it_can_add_standups() {
         let mut manager = Manager::from_reader("[]".as_bytes()).unwrap();
         let standup = Standup::new();
         manager.insert(standup.clone());
-        assert_eq!(manager.get(&standup.date).unwrap(), standup);
+        assert_eq!(manager.get_by(&standup.date, None).unwrap(), standup);
     }
 
     #[test]
     fn it_can_delete_a_standup() {
         let mut manager = Manager::from_reader("[]".as_bytes()).unwrap();
-        manager.insert(Standup::from_date(Local.ymd(2015, 1, 1)));
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()));
         assert_eq!(manager.standups.len(), 1);
-        manager.delete(&Local.ymd(2015, 1, 1));
+        manager.delete_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None);
         assert_eq!(manager.standups.len(), 0);
     }
-}
 
+    #[test]
+    fn it_can_keep_a_standup_per_person() {
+        let mut manager = Manager::new();
+        let date = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+        manager.insert(Standup::from_date(date.clone()).set_author(Some("bob".to_string())).add(Aspect::Today, "b"));
+        manager.insert(Standup::from_date(date.clone()).set_author(Some("alice".to_string())).add(Aspect::Today, "a"));
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 2).unwrap()).set_author(Some("alice".to_string())));
+        assert!(manager.get_by(&date, None).is_none());
+        assert_eq!(manager.get_by(&date, Some("bob")).unwrap().today, vec!["b"]);
+        let authors: Vec<Option<String>> = manager.day(&date).iter().map(|s| s.author.clone()).collect();
+        assert_eq!(authors, vec![Some("alice".to_string()), Some("bob".to_string())]);
+
+        manager.delete_by(&date, Some("alice"));
+        assert_eq!(manager.day(&date).len(), 1);
+    }
+
+    #[test]
+    fn it_can_merge_another_manager() {
+        let mut ours = Manager::from_reader("[{\"date\":\"2015-01-01\",\"today\":[\"a\"]}]".as_bytes()).unwrap();
+        let theirs = Manager::from_reader("[{\"date\":\"2015-01-01\",\"today\":[\"a\",\"b\"]},\
+                                            {\"date\":\"2015-01-02\"}]".as_bytes()).unwrap();
+        let conflicts = ours.merge(theirs);
+        assert_eq!(conflicts.len(), 0);
+        assert_eq!(ours.standups.len(), 2);
+        assert_eq!(ours.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().today, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn it_will_report_merge_conflicts() {
+        let mut ours = Manager::new();
+        ours.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Blocker, "mine"));
+        let mut theirs = Manager::new();
+        theirs.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Blocker, "theirs"));
+        let conflicts = ours.merge(theirs);
+        assert_eq!(conflicts, vec![Conflict { date: NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), aspect: Aspect::Blocker }]);
+        assert_eq!(ours.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().blocker, vec!["mine", "theirs"]);
+    }
+}
@@ -1,6 +1,8 @@
 mod standup;
 mod manager;
+mod layout;
 
 pub use self::standup::Standup;
-pub use self::manager::Manager;
-pub use self::standup::Aspect;
+pub use self::manager::{Manager, Conflict, Change};
+pub use self::standup::{Aspect, tag_of, parse_entries};
+pub use self::layout::Layout;
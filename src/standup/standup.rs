@@ -1,41 +1,71 @@
 use std::vec::Vec;
 use std::fmt;
 use std::fmt::Display;
-use chrono::Date;
-use chrono::offset::local::Local;
+use chrono::{NaiveDate, Local};
+use standup::Layout;
+use ::StandupError;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct Standup {
     pub today: Vec<String>,
     pub yesterday: Vec<String>,
     pub blocker: Vec<String>,
-    pub date: Date<Local>,
+    pub date: NaiveDate,
+    /// Who the standup belongs to when several people share one log.
+    pub author: Option<String>,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Aspect {
     Today,
     Yesterday,
     Blocker
 }
 
+impl Aspect {
+    /// All of the aspects in the order they are displayed.
+    pub fn all() -> Vec<Aspect> {
+        vec![Aspect::Today, Aspect::Yesterday, Aspect::Blocker]
+    }
+
+    pub fn from_name(name: &str) -> Option<Aspect> {
+        match name {
+            "today" => Some(Aspect::Today),
+            "yesterday" => Some(Aspect::Yesterday),
+            "blocker" => Some(Aspect::Blocker),
+            _ => None
+        }
+    }
+
+    /// Reads the prefix of a line in a bulk add, `t`, `y` or `b`, or the
+    /// whole name.
+    pub fn from_prefix(prefix: &str) -> Option<Aspect> {
+        match prefix {
+            "t" => Some(Aspect::Today),
+            "y" => Some(Aspect::Yesterday),
+            "b" => Some(Aspect::Blocker),
+            name => Aspect::from_name(name),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Aspect::Today => "today",
+            Aspect::Yesterday => "yesterday",
+            Aspect::Blocker => "blocker"
+        }
+    }
+}
+
+impl Display for Aspect {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
 impl Display for Standup {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        try!(write!(f, "{}\n", self.date.format("%F - %A")));
-        try!(write!(f, "  today:\n"));
-        for (i, message) in self.today.iter().enumerate() {
-            try!(write!(f, "    {}. {}\n", i + 1, message));
-        }
-        try!(write!(f, "  yesterday:\n"));
-        for (i, message) in self.yesterday.iter().enumerate() {
-            try!(write!(f, "    {}. {}\n", i + 1, message));
-        }
-        if self.is_blocked() {
-            try!(write!(f, "  blocker:\n"));
-            for (i, message) in self.blocker.iter().enumerate() {
-                try!(write!(f, "    {}. {}\n", i + 1, message));
-            }
-        }
-        Ok(())
+        write!(f, "{}", Layout::default().render(self))
     }
 }
 
@@ -45,16 +75,18 @@ impl Standup {
             today: vec![],
             yesterday: vec![],
             blocker: vec![],
-            date: Local::today()
+            date: Local::now().date_naive(),
+            author: None
         }
     }
 
-    pub fn from_date(date: Date<Local>) -> Standup {
+    pub fn from_date(date: NaiveDate) -> Standup {
         Standup {
             today: vec![],
             yesterday: vec![],
             blocker: vec![],
-            date: date
+            date: date,
+            author: None
         }
     }
 
@@ -62,10 +94,34 @@ impl Standup {
         !self.blocker.is_empty()
     }
 
-    pub fn set_date(self, date: Date<Local>) -> Standup {
+    /// The `#tags` used in any of the entries, lowercased and in order.
+    pub fn tags(&self) -> Vec<String> {
+        let mut tags: Vec<String> = Aspect::all().into_iter()
+            .flat_map(|aspect| self.messages(aspect).iter())
+            .flat_map(|message| message.split_whitespace())
+            .filter_map(tag_of)
+            .collect();
+        tags.sort();
+        tags.dedup();
+        tags
+    }
+
+    pub fn messages(&self, aspect: Aspect) -> &Vec<String> {
+        match aspect {
+            Aspect::Today => &self.today,
+            Aspect::Yesterday => &self.yesterday,
+            Aspect::Blocker => &self.blocker
+        }
+    }
+
+    pub fn set_date(self, date: NaiveDate) -> Standup {
         Standup { date: date, .. self }
     }
 
+    pub fn set_author(self, author: Option<String>) -> Standup {
+        Standup { author: author, .. self }
+    }
+
     pub fn remove(self, aspect: Aspect, index: usize) -> Standup {
         match aspect {
             Aspect::Today => self.remove_today(index),
@@ -74,6 +130,48 @@ impl Standup {
         }
     }
 
+    /// Swaps the entry at the index for a new message, or returns a copy
+    /// when the index is outside the range.
+    pub fn replace(self, aspect: Aspect, index: usize, msg: &str) -> Standup {
+        let mut messages = self.messages(aspect).clone();
+        if index >= messages.len() { return self }
+        messages[index] = msg.to_string();
+        self.with_messages(aspect, messages)
+    }
+
+    /// Takes the entry at the index out of the standup, failing when there
+    /// is no such entry.
+    pub fn take(self, aspect: Aspect, index: usize) -> Result<(Standup, String), StandupError> {
+        let message = try!(self.messages(aspect).get(index).cloned().ok_or(StandupError::LineNotFound));
+        Ok((self.remove(aspect, index), message))
+    }
+
+    /// Puts an entry in at the index, moving the ones after it down. The
+    /// index may be one past the last entry, to add it at the end.
+    pub fn insert(self, aspect: Aspect, index: usize, msg: &str) -> Result<Standup, StandupError> {
+        let mut messages = self.messages(aspect).clone();
+        if index > messages.len() {
+            return Err(StandupError::LineNotFound);
+        }
+        messages.insert(index, msg.to_string());
+        Ok(self.with_messages(aspect, messages))
+    }
+
+    /// Moves an entry to another position in the same aspect.
+    pub fn move_within(self, aspect: Aspect, from: usize, to: usize) -> Result<Standup, StandupError> {
+        if to >= self.messages(aspect).len() {
+            return Err(StandupError::LineNotFound);
+        }
+        let (standup, message) = try!(self.take(aspect, from));
+        standup.insert(aspect, to, &message)
+    }
+
+    /// Moves an entry to the end of another aspect.
+    pub fn move_to(self, aspect: Aspect, index: usize, target: Aspect) -> Result<Standup, StandupError> {
+        let (standup, message) = try!(self.take(aspect, index));
+        Ok(standup.add(target, &message))
+    }
+
     pub fn add(self, aspect: Aspect, msg: &str) -> Standup {
         match aspect {
             Aspect::Today => self.add_today(&msg),
@@ -82,6 +180,55 @@ impl Standup {
         }
     }
 
+    /// Merges another standup for the same day into this one. Each aspect
+    /// keeps its own entries in order, and any entries only the other side has
+    /// are slotted in before the next entry both sides share. Identical lines
+    /// are only kept once.
+    pub fn merge(self, other: &Standup) -> Standup {
+        Aspect::all().into_iter().fold(self, |standup, aspect| {
+            let merged = Standup::union(standup.messages(aspect), other.messages(aspect));
+            standup.with_messages(aspect, merged)
+        })
+    }
+
+    /// Returns the aspects where both standups have entries the other does
+    /// not. Merging keeps both sides, but they may need a second look.
+    pub fn conflicts(&self, other: &Standup) -> Vec<Aspect> {
+        Aspect::all().into_iter().filter(|&aspect| {
+            let ours = self.messages(aspect);
+            let theirs = other.messages(aspect);
+            ours.iter().any(|m| !theirs.contains(m)) && theirs.iter().any(|m| !ours.contains(m))
+        }).collect()
+    }
+
+    fn with_messages(self, aspect: Aspect, messages: Vec<String>) -> Standup {
+        match aspect {
+            Aspect::Today => Standup { today: messages, .. self },
+            Aspect::Yesterday => Standup { yesterday: messages, .. self },
+            Aspect::Blocker => Standup { blocker: messages, .. self }
+        }
+    }
+
+    fn union(ours: &Vec<String>, theirs: &Vec<String>) -> Vec<String> {
+        let mut merged: Vec<String> = Vec::with_capacity(ours.len() + theirs.len());
+        for message in ours {
+            if !merged.contains(message) { merged.push(message.clone()); }
+        }
+        let mut pending: Vec<String> = vec![];
+        for message in theirs {
+            match merged.iter().position(|m| m == message) {
+                Some(index) => {
+                    for (offset, line) in pending.drain(..).enumerate() {
+                        merged.insert(index + offset, line);
+                    }
+                },
+                None => if !pending.contains(message) { pending.push(message.clone()) }
+            }
+        }
+        merged.extend(pending);
+        merged
+    }
+
     fn remove_blocker(self, index: usize) -> Standup {
         Standup { blocker: Standup::delete(&self.blocker, index), ..self }
     }
@@ -123,6 +270,37 @@ impl Standup {
     }
 }
 
+/// The tag a word is, such as `release` for `#Release,`. Trailing punctuation
+/// is not part of the tag.
+pub fn tag_of(word: &str) -> Option<String> {
+    if !word.starts_with("#") {
+        return None;
+    }
+    let tag: String = word[1..].chars()
+        .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    if tag.is_empty() { None } else { Some(tag.to_lowercase()) }
+}
+
+/// Reads entries written one per line as `t: message`, `y: message` or
+/// `b: message`. Blank lines and lines starting with `#` are skipped.
+pub fn parse_entries(text: &str) -> Result<Vec<(Aspect, String)>, StandupError> {
+    let mut entries = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("#") {
+            continue;
+        }
+        let mut parts = line.splitn(2, ':');
+        let aspect = parts.next().and_then(|prefix| Aspect::from_prefix(prefix.trim().to_lowercase().as_str()));
+        match (aspect, parts.next().map(|message| message.trim())) {
+            (Some(aspect), Some(message)) if !message.is_empty() => entries.push((aspect, message.to_string())),
+            _ => return Err(StandupError::InvalidEntry(number + 1)),
+        }
+    }
+    Ok(entries)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -139,6 +317,26 @@ mod test {
         assert_eq!(standup.is_blocked(), false);
     }
 
+    #[test]
+    fn it_can_find_tags() {
+        let standup = Standup::new()
+            .add(Aspect::Today, "ship the #Release, then #docs")
+            .add(Aspect::Blocker, "#release is waiting on # and #1");
+        assert_eq!(standup.tags(), vec!["1", "docs", "release"]);
+    }
+
+    #[test]
+    fn it_can_parse_prefixed_entries() {
+        let entries = parse_entries("t: review\n\n# notes\nY: shipped: v2\nblocker: ops\n").unwrap();
+        assert_eq!(entries, vec![(Aspect::Today, "review".to_string()),
+                                 (Aspect::Yesterday, "shipped: v2".to_string()),
+                                 (Aspect::Blocker, "ops".to_string())]);
+        match parse_entries("t: review\nx: what\n") {
+            Err(StandupError::InvalidEntry(2)) => {},
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
     #[test]
     fn it_can_add_to_today() {
         let standup = Standup::new().add(Aspect::Today, "hello world");
@@ -175,6 +373,113 @@ mod test {
         assert_eq!(standup.blocker.len(), 3);
     }
 
+    #[test]
+    fn it_can_look_up_messages_by_aspect() {
+        let standup = Standup::new()
+            .add(Aspect::Today, "today")
+            .add(Aspect::Blocker, "blocker");
+        assert_eq!(standup.messages(Aspect::Today), &vec!["today".to_string()]);
+        assert_eq!(standup.messages(Aspect::Yesterday).len(), 0);
+        assert_eq!(standup.messages(Aspect::Blocker), &vec!["blocker".to_string()]);
+    }
+
+    #[test]
+    fn it_can_round_trip_aspect_names() {
+        for aspect in Aspect::all() {
+            assert_eq!(Aspect::from_name(aspect.name()), Some(aspect));
+        }
+        assert_eq!(Aspect::from_name("tomorrow"), None);
+    }
+
+    #[test]
+    fn it_can_merge_entries_from_both_sides() {
+        let ours = Standup::new()
+            .add(Aspect::Today, "a")
+            .add(Aspect::Today, "b")
+            .add(Aspect::Today, "d");
+        let theirs = Standup::new()
+            .add(Aspect::Today, "a")
+            .add(Aspect::Today, "c")
+            .add(Aspect::Today, "d")
+            .add(Aspect::Blocker, "blocked");
+        let merged = ours.merge(&theirs);
+        assert_eq!(merged.today, vec!["a", "b", "c", "d"]);
+        assert_eq!(merged.blocker, vec!["blocked"]);
+    }
+
+    #[test]
+    fn it_will_dedupe_identical_lines_when_merging() {
+        let ours = Standup::new()
+            .add(Aspect::Yesterday, "same")
+            .add(Aspect::Yesterday, "same");
+        let theirs = Standup::new().add(Aspect::Yesterday, "same");
+        assert_eq!(ours.merge(&theirs).yesterday, vec!["same"]);
+    }
+
+    #[test]
+    fn it_will_put_new_leading_lines_first_when_merging() {
+        let ours = Standup::new().add(Aspect::Today, "b");
+        let theirs = Standup::new()
+            .add(Aspect::Today, "a")
+            .add(Aspect::Today, "b");
+        assert_eq!(ours.merge(&theirs).today, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn it_can_detect_conflicts() {
+        let ours = Standup::new()
+            .add(Aspect::Today, "mine")
+            .add(Aspect::Blocker, "shared");
+        let theirs = Standup::new()
+            .add(Aspect::Today, "theirs")
+            .add(Aspect::Blocker, "shared")
+            .add(Aspect::Blocker, "more");
+        assert_eq!(ours.conflicts(&theirs), vec![Aspect::Today]);
+    }
+
+    #[test]
+    fn it_will_keep_the_author_when_changed() {
+        let standup = Standup::new()
+            .set_author(Some("alice".to_string()))
+            .add(Aspect::Today, "today")
+            .remove(Aspect::Today, 0);
+        assert_eq!(standup.author, Some("alice".to_string()));
+    }
+
+    #[test]
+    fn it_can_replace_an_entry() {
+        let standup = Standup::new()
+            .add(Aspect::Today, "first")
+            .add(Aspect::Today, "second")
+            .replace(Aspect::Today, 1, "changed");
+        assert_eq!(standup.today, vec!["first", "changed"]);
+        assert_eq!(standup.replace(Aspect::Today, 5, "nope").today.len(), 2);
+    }
+
+    #[test]
+    fn it_can_move_an_entry_within_an_aspect() {
+        let standup = Standup::new()
+            .add(Aspect::Today, "one")
+            .add(Aspect::Today, "two")
+            .add(Aspect::Today, "three")
+            .move_within(Aspect::Today, 2, 0)
+            .unwrap();
+        assert_eq!(standup.today, vec!["three", "one", "two"]);
+        assert!(standup.clone().move_within(Aspect::Today, 0, 3).is_err());
+        assert!(standup.move_within(Aspect::Blocker, 0, 0).is_err());
+    }
+
+    #[test]
+    fn it_can_move_an_entry_to_another_aspect() {
+        let standup = Standup::new()
+            .add(Aspect::Today, "waiting on ops")
+            .move_to(Aspect::Today, 0, Aspect::Blocker)
+            .unwrap();
+        assert_eq!(standup.today.len(), 0);
+        assert_eq!(standup.blocker, vec!["waiting on ops"]);
+        assert!(standup.move_to(Aspect::Today, 0, Aspect::Blocker).is_err());
+    }
+
     #[test]
     fn it_can_remove_a_today() {
         let standup = Standup::new()
@@ -0,0 +1,321 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::{self, Read, Write};
+use std::collections::BTreeSet;
+use chrono::*;
+use serde_json::builder::ObjectBuilder;
+use serde_json::{from_str, Value};
+use ::CliError;
+use ::StandupError;
+use standup::{Standup, Manager};
+
+/// Backups are named by when they were taken, down to the nanosecond so that
+/// two writes in the same second keep a backup each. Older backups named to
+/// the second still read.
+const ID_FORMAT: &'static str = "%Y%m%dT%H%M%S%.f";
+
+/// How many backups to hold on to. The newest `keep_last` are always kept,
+/// along with the newest backup of each of the last `daily` days and of each
+/// of the last `weekly` weeks.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Retention {
+    pub keep_last: usize,
+    pub daily: usize,
+    pub weekly: usize,
+}
+
+impl Retention {
+    pub fn default() -> Retention {
+        Retention { keep_last: 10, daily: 7, weekly: 4 }
+    }
+
+    /// Works out which of the backups to keep. The ids do not need to be in
+    /// any particular order.
+    pub fn retained(&self, ids: &[NaiveDateTime], now: NaiveDateTime) -> BTreeSet<NaiveDateTime> {
+        let mut newest_first: Vec<NaiveDateTime> = ids.to_vec();
+        newest_first.sort_by(|a, b| b.cmp(a));
+
+        let mut keep: BTreeSet<NaiveDateTime> = newest_first.iter().take(self.keep_last).cloned().collect();
+        let today = now.date();
+        let mut days = BTreeSet::new();
+        let mut weeks = BTreeSet::new();
+        for id in newest_first.iter() {
+            let day = id.date();
+            if day > today - Duration::days(self.daily as i64) && days.insert(day) {
+                keep.insert(*id);
+            }
+            let week = day.iso_week();
+            if day > today - Duration::weeks(self.weekly as i64) && weeks.insert((week.year(), week.week())) {
+                keep.insert(*id);
+            }
+        }
+        keep
+    }
+}
+
+///
+/// ## Backups
+///
+/// Timestamped copies of the data file, taken before each write, in a
+/// directory next to it. The copies are byte for byte, so an encrypted file
+/// stays encrypted.
+///
+pub struct Backups {
+    dir: PathBuf,
+    retention: Retention,
+}
+
+impl Backups {
+    /// Backups are on when the directory exists. The retention policy is kept
+    /// inside it.
+    pub fn open(dir: PathBuf) -> Result<Option<Backups>, CliError> {
+        if !dir.is_dir() {
+            return Ok(None);
+        }
+        let retention = try!(Backups::read_retention(&dir.join("retention.json")));
+        Ok(Some(Backups { dir: dir, retention: retention }))
+    }
+
+    pub fn enable(dir: PathBuf, retention: Retention) -> Result<Backups, CliError> {
+        try!(fs::create_dir_all(&dir).map_err(CliError::Io));
+        let json = format!("{}\n", ObjectBuilder::new()
+            .insert("keep_last", retention.keep_last)
+            .insert("daily", retention.daily)
+            .insert("weekly", retention.weekly)
+            .unwrap());
+        try!(fs::File::create(dir.join("retention.json"))
+             .and_then(|mut file| file.write_all(json.as_bytes()))
+             .map_err(CliError::Io));
+        Ok(Backups { dir: dir, retention: retention })
+    }
+
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    fn read_retention(path: &Path) -> Result<Retention, CliError> {
+        let default = Retention::default();
+        if !path.is_file() {
+            return Ok(default);
+        }
+        let mut buf = String::new();
+        try!(fs::File::open(path).and_then(|mut file| file.read_to_string(&mut buf)).map_err(CliError::Io));
+        let value: Value = try!(from_str(&buf).map_err(CliError::Parse));
+        let field = |key: &str, fallback: usize| {
+            value.find(key).and_then(|v| v.as_u64()).map_or(fallback, |n| n as usize)
+        };
+        Ok(Retention {
+            keep_last: field("keep_last", default.keep_last),
+            daily: field("daily", default.daily),
+            weekly: field("weekly", default.weekly),
+        })
+    }
+
+    /// Copies the data file into the backups directory and prunes anything the
+    /// retention policy no longer covers. Does nothing if there is no data yet,
+    /// and never writes over an existing backup.
+    pub fn take(&self, data: &Path) -> Result<Option<String>, CliError> {
+        let empty = fs::metadata(data).map(|meta| meta.len() == 0).unwrap_or(true);
+        if empty {
+            return Ok(None);
+        }
+        let now = Local::now().naive_local();
+        let id = format_id(&now);
+        let mut from = try!(fs::File::open(data).map_err(CliError::Io));
+        let mut to = try!(OpenOptions::new().write(true).create_new(true).open(self.path(&id)).map_err(CliError::Io));
+        try!(io::copy(&mut from, &mut to).map_err(CliError::Io));
+        try!(self.prune(now));
+        Ok(Some(id))
+    }
+
+    fn prune(&self, now: NaiveDateTime) -> Result<(), CliError> {
+        let ids = try!(self.list());
+        let keep = self.retention.retained(&ids, now);
+        for id in ids.iter().filter(|id| !keep.contains(id)) {
+            try!(fs::remove_file(self.path(&format_id(id))).map_err(CliError::Io));
+        }
+        Ok(())
+    }
+
+    /// The backups that exist, oldest first.
+    pub fn list(&self) -> Result<Vec<NaiveDateTime>, CliError> {
+        let mut ids = vec![];
+        for entry in try!(fs::read_dir(&self.dir).map_err(CliError::Io)) {
+            let path = try!(entry.map_err(CliError::Io)).path();
+            let id = path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .and_then(|stem| NaiveDateTime::parse_from_str(stem, ID_FORMAT).ok());
+            if path.extension().map_or(false, |ext| ext == "json") {
+                if let Some(id) = id { ids.push(id); }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    /// Reads a backup by its id. Anything that is not shaped like one, such as
+    /// a path or the retention file's name, is not found rather than read.
+    pub fn read(&self, id: &str) -> Result<Vec<u8>, CliError> {
+        if NaiveDateTime::parse_from_str(id, ID_FORMAT).is_err() {
+            return Err(CliError::Cli(StandupError::BackupNotFound));
+        }
+        let path = self.path(id);
+        if !path.is_file() {
+            return Err(CliError::Cli(StandupError::BackupNotFound));
+        }
+        let mut bytes = vec![];
+        try!(fs::File::open(path).and_then(|mut file| file.read_to_end(&mut bytes)).map_err(CliError::Io));
+        Ok(bytes)
+    }
+}
+
+/// The name a backup taken at the time goes by, as `restore` takes it.
+pub fn format_id(id: &NaiveDateTime) -> String {
+    id.format(ID_FORMAT).to_string()
+}
+
+/// Describes what restoring a backup would change, one line per day:
+/// `+` for days the backup brings back, `-` for days it would remove and `~`
+/// for days whose entries differ.
+pub fn summarize(current: &Manager, backup: &Manager) -> Vec<String> {
+    let entries = |standup: &Standup| standup.today.len() + standup.yesterday.len() + standup.blocker.len();
+    let find = |manager: &Manager, key: &(NaiveDate, Option<String>)| {
+        manager.get_by(&key.0, key.1.as_ref().map(|author| author.as_str()))
+    };
+    let mut keys: Vec<(NaiveDate, Option<String>)> = current.standups().iter()
+        .chain(backup.standups().iter())
+        .map(|standup| (standup.date.clone(), standup.author.clone()))
+        .collect();
+    keys.sort();
+    keys.dedup();
+
+    keys.iter().filter_map(|key| {
+        let day = match key.1 {
+            Some(ref author) => format!("{} {}", key.0.format("%F"), author),
+            None => key.0.format("%F").to_string(),
+        };
+        match (find(current, key), find(backup, key)) {
+            (None, Some(theirs)) => Some(format!("+ {} ({} entries)", day, entries(&theirs))),
+            (Some(ours), None) => Some(format!("- {} ({} entries)", day, entries(&ours))),
+            (Some(ours), Some(theirs)) => {
+                if ours == theirs { None } else {
+                    Some(format!("~ {} ({} -> {} entries)", day, entries(&ours), entries(&theirs)))
+                }
+            },
+            (None, None) => None,
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use store::test::scratch;
+    use standup::{Standup, Aspect, Manager};
+    use std::fs;
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2016, 3, day).unwrap().and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn it_will_keep_the_last_few() {
+        let retention = Retention { keep_last: 2, daily: 0, weekly: 0 };
+        let ids = vec![at(1, 9), at(1, 10), at(1, 11)];
+        let keep = retention.retained(&ids, at(1, 12));
+        assert_eq!(keep.into_iter().collect::<Vec<_>>(), vec![at(1, 10), at(1, 11)]);
+    }
+
+    #[test]
+    fn it_will_keep_the_newest_of_each_day_for_a_week() {
+        let retention = Retention { keep_last: 0, daily: 7, weekly: 0 };
+        let ids = vec![at(1, 9), at(10, 9), at(10, 17), at(11, 9), at(11, 12)];
+        let keep = retention.retained(&ids, at(11, 18));
+        assert_eq!(keep.into_iter().collect::<Vec<_>>(), vec![at(10, 17), at(11, 12)]);
+    }
+
+    #[test]
+    fn it_will_keep_the_newest_of_each_week_for_a_month() {
+        let retention = Retention { keep_last: 0, daily: 0, weekly: 4 };
+        // The 7th and 8th are a Monday and Tuesday in the same week.
+        let ids = vec![at(1, 9), at(7, 9), at(8, 9), at(29, 9)];
+        let keep = retention.retained(&ids, NaiveDate::from_ymd_opt(2016, 4, 30).unwrap().and_hms_opt(0, 0, 0).unwrap());
+        assert_eq!(keep.len(), 0);
+        let keep = retention.retained(&ids, at(29, 10));
+        assert_eq!(keep.into_iter().collect::<Vec<_>>(), vec![at(8, 9), at(29, 9)]);
+    }
+
+    #[test]
+    fn it_will_remember_the_retention_policy() {
+        let dir = scratch("backup-retention").join("backups");
+        let retention = Retention { keep_last: 3, daily: 2, weekly: 1 };
+        Backups::enable(dir.clone(), retention).unwrap();
+        assert_eq!(Backups::open(dir).unwrap().unwrap().retention(), retention);
+    }
+
+    #[test]
+    fn it_will_be_off_without_a_directory() {
+        assert!(Backups::open(scratch("backup-off").join("backups")).unwrap().is_none());
+    }
+
+    #[test]
+    fn it_can_take_and_read_a_backup() {
+        let root = scratch("backup-take");
+        let data = root.join("standup.json");
+        fs::File::create(&data).unwrap().write_all(b"[]").unwrap();
+        let backups = Backups::enable(root.join("backups"), Retention::default()).unwrap();
+        let id = backups.take(&data).unwrap().unwrap();
+        assert_eq!(backups.list().unwrap().len(), 1);
+        assert_eq!(backups.read(&id).unwrap(), b"[]".to_vec());
+        assert!(backups.read("19990101T000000").is_err());
+    }
+
+    #[test]
+    fn it_will_only_read_backups_by_id() {
+        let root = scratch("backup-traversal");
+        fs::File::create(root.join("outside.json")).unwrap().write_all(b"[]").unwrap();
+        let backups = Backups::enable(root.join("backups"), Retention::default()).unwrap();
+        for id in &["../outside", "retention", "20160301T090000/../../outside"] {
+            match backups.read(id) {
+                Err(CliError::Cli(StandupError::BackupNotFound)) => {},
+                other => panic!("read {} gave {:?}", id, other),
+            }
+        }
+    }
+
+    #[test]
+    fn it_will_keep_a_backup_per_write_within_a_second() {
+        let root = scratch("backup-same-second");
+        let data = root.join("standup.json");
+        fs::File::create(&data).unwrap().write_all(b"[]").unwrap();
+        let backups = Backups::enable(root.join("backups"), Retention::default()).unwrap();
+        fs::File::create(backups.path("20160301T090000")).unwrap();
+        let first = backups.take(&data).unwrap().unwrap();
+        let second = backups.take(&data).unwrap().unwrap();
+        assert!(first != second);
+        assert_eq!(backups.list().unwrap()[0], at(1, 9));
+        assert_eq!(backups.list().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn it_can_summarize_a_restore() {
+        let mut current = Manager::new();
+        current.insert(Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 1).unwrap()).add(Aspect::Today, "a"));
+        current.insert(Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 2).unwrap()).add(Aspect::Today, "b"));
+        current.insert(Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 3).unwrap()));
+        let mut backup = Manager::new();
+        backup.insert(Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 1).unwrap()).add(Aspect::Today, "a"));
+        backup.insert(Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 2).unwrap()));
+        backup.insert(Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap()).add(Aspect::Blocker, "c"));
+        assert_eq!(summarize(&current, &backup), vec![
+            "~ 2016-03-02 (1 -> 0 entries)",
+            "- 2016-03-03 (0 entries)",
+            "+ 2016-03-04 (1 entries)",
+        ]);
+    }
+}
@@ -0,0 +1,202 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::Command;
+use ::CliError;
+use ::StandupError;
+use jsonify;
+
+pub fn is_repo(dir: &Path) -> bool {
+    dir.join(".git").is_dir()
+}
+
+/// Clones the remote into the directory under the given name, or starts a
+/// fresh repository when there is no remote.
+pub fn init(dir: &Path, url: Option<&str>, name: &str) -> Result<(), CliError> {
+    let dir_str = try!(dir.to_str().ok_or(CliError::Cli(StandupError::DataFilepathInvalid)));
+    match url {
+        Some(url) => git(None, &["clone", "--quiet", "--origin", name, url, dir_str]).map(|_| ()),
+        None => git(None, &["init", "--quiet", dir_str]).map(|_| ()),
+    }
+}
+
+/// Stages everything and commits it, doing nothing when there are no changes.
+pub fn commit(dir: &Path, message: &str) -> Result<(), CliError> {
+    try!(run(dir, &["add", "--all", "."]));
+    let status = try!(run(dir, &["status", "--porcelain"]));
+    if status.trim().is_empty() {
+        return Ok(());
+    }
+    run_as_author(dir, &["commit", "--quiet", "-m", message]).map(|_| ())
+}
+
+/// Rebases local commits onto the remote and pushes them. A day both sides
+/// changed is merged entry by entry; any other conflict aborts the rebase so
+/// the repository is left as it was.
+pub fn sync(dir: &Path, remote: &str) -> Result<(), CliError> {
+    let remotes = try!(run(dir, &["remote"]));
+    if !remotes.lines().any(|name| name.trim() == remote) {
+        return Err(CliError::Cli(StandupError::NoRemote));
+    }
+    let branch = try!(run(dir, &["rev-parse", "--abbrev-ref", "HEAD"]));
+    let branch = branch.trim();
+    let heads = try!(run(dir, &["ls-remote", "--heads", remote, branch]));
+    if !heads.trim().is_empty() {
+        if let Err(err) = pull(dir, remote, branch) {
+            let _ = run(dir, &["rebase", "--abort"]);
+            return Err(err);
+        }
+    }
+    run(dir, &["push", "--quiet", "--set-upstream", remote, branch]).map(|_| ())
+}
+
+/// Pulls with a rebase, settling each stop on conflicting day files by
+/// merging them and carrying on.
+fn pull(dir: &Path, remote: &str, branch: &str) -> Result<(), CliError> {
+    let mut result = run_as_author(dir, &["pull", "--quiet", "--rebase", remote, branch]);
+    while let Err(err) = result {
+        let conflicted = try!(run(dir, &["diff", "--name-only", "--diff-filter=U"]));
+        if conflicted.trim().is_empty() {
+            return Err(err);
+        }
+        for path in conflicted.lines() {
+            try!(merge_day(dir, path));
+        }
+        // The merge can leave the commit with nothing left to change.
+        let staged = try!(run(dir, &["diff", "--cached", "--name-only"]));
+        result = if staged.trim().is_empty() {
+            run(dir, &["rebase", "--skip"])
+        } else {
+            run_as_author(dir, &["-c", "core.editor=true", "rebase", "--continue"])
+        };
+    }
+    Ok(())
+}
+
+/// Writes the union of both sides' standups into a day file they both
+/// changed. When one side deleted the day, the other side's standup is kept.
+fn merge_day(dir: &Path, path: &str) -> Result<(), CliError> {
+    let unmergeable = || CliError::Git(format!("{} was changed on both sides and is not a standup", path));
+    let side = |stage: u8| run(dir, &["show", &format!(":{}:{}", stage, path)]).ok();
+    let read = |json: String| jsonify::deserialize_day(json).map_err(|_| unmergeable());
+    let merged = match (side(2), side(3)) {
+        (Some(ours), Some(theirs)) => try!(read(ours)).merge(&try!(read(theirs))),
+        (Some(one), None) | (None, Some(one)) => try!(read(one)),
+        (None, None) => return Err(unmergeable()),
+    };
+    try!(fs::File::create(dir.join(path))
+         .and_then(|mut file| file.write_all(jsonify::serialize_day(&merged).as_bytes()))
+         .map_err(CliError::Io));
+    run(dir, &["add", "--", path]).map(|_| ())
+}
+
+pub fn run(dir: &Path, args: &[&str]) -> Result<String, CliError> {
+    git(Some(dir), args)
+}
+
+/// Runs a command that creates commits, falling back to a placeholder
+/// identity when git has not been told who the user is.
+fn run_as_author(dir: &Path, args: &[&str]) -> Result<String, CliError> {
+    if run(dir, &["config", "user.email"]).is_ok() {
+        run(dir, args)
+    } else {
+        let mut with_identity = vec!["-c", "user.name=standup", "-c", "user.email=standup@localhost"];
+        with_identity.extend_from_slice(args);
+        run(dir, &with_identity)
+    }
+}
+
+fn git(dir: Option<&Path>, args: &[&str]) -> Result<String, CliError> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = try!(command.args(args).output().map_err(CliError::Io));
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(CliError::Git(format!("git {} failed: {}",
+                                  args.join(" "),
+                                  String::from_utf8_lossy(&output.stderr).trim())))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use store::Store;
+    use store::test::scratch;
+    use standup::{Standup, Aspect};
+    use chrono::*;
+
+    #[test]
+    fn it_can_sync_two_clones_through_a_bare_repository() {
+        let root = scratch("git-sync");
+        let remote = root.join("remote.git");
+        git(None, &["init", "--quiet", "--bare", remote.to_str().unwrap()]).unwrap();
+        let remote = remote.to_str().unwrap();
+
+        let laptop = Store::Git(root.join("laptop"));
+        let desktop = Store::Git(root.join("desktop"));
+        init(&root.join("laptop"), Some(remote), "origin").unwrap();
+
+        let mut manager = laptop.load().unwrap();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "from laptop"));
+        laptop.save(&mut manager, "Add today entry for 2015-01-01").unwrap();
+        laptop.sync("origin").unwrap();
+
+        init(&root.join("desktop"), Some(remote), "origin").unwrap();
+        let mut manager = desktop.load().unwrap();
+        assert_eq!(manager.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().today, vec!["from laptop"]);
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 2).unwrap()).add(Aspect::Today, "from desktop"));
+        desktop.save(&mut manager, "Add today entry for 2015-01-02").unwrap();
+
+        let mut manager = laptop.load().unwrap();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 3).unwrap()).add(Aspect::Blocker, "from laptop"));
+        laptop.save(&mut manager, "Add blocker entry for 2015-01-03").unwrap();
+
+        desktop.sync("origin").unwrap();
+        laptop.sync("origin").unwrap();
+        assert_eq!(laptop.load().unwrap().standups().len(), 3);
+    }
+
+    #[test]
+    fn it_will_merge_a_day_changed_on_both_sides() {
+        let root = scratch("git-sync-same-day");
+        let remote = root.join("remote.git");
+        git(None, &["init", "--quiet", "--bare", remote.to_str().unwrap()]).unwrap();
+        let remote = remote.to_str().unwrap();
+        let date = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+
+        let laptop = Store::Git(root.join("laptop"));
+        let desktop = Store::Git(root.join("desktop"));
+        init(&root.join("laptop"), Some(remote), "upstream").unwrap();
+        let mut manager = laptop.load().unwrap();
+        manager.insert(Standup::from_date(date).add(Aspect::Today, "shared"));
+        laptop.save(&mut manager, "Add today entry for 2015-01-01").unwrap();
+        laptop.sync("upstream").unwrap();
+        init(&root.join("desktop"), Some(remote), "upstream").unwrap();
+
+        let mut manager = laptop.load().unwrap();
+        manager.insert(manager.get_by(&date, None).unwrap().add(Aspect::Today, "from laptop"));
+        laptop.save(&mut manager, "Add today entry for 2015-01-01").unwrap();
+        let mut manager = desktop.load().unwrap();
+        manager.insert(manager.get_by(&date, None).unwrap().add(Aspect::Today, "from desktop"));
+        desktop.save(&mut manager, "Add today entry for 2015-01-01").unwrap();
+
+        laptop.sync("upstream").unwrap();
+        desktop.sync("upstream").unwrap();
+        laptop.sync("upstream").unwrap();
+        for store in &[laptop, desktop] {
+            let standup = store.load().unwrap().get_by(&date, None).unwrap();
+            assert_eq!(standup.today, vec!["shared", "from laptop", "from desktop"]);
+        }
+    }
+
+    #[test]
+    fn it_will_not_sync_without_a_remote() {
+        let dir = scratch("git-no-remote").join("data");
+        init(&dir, None, "origin").unwrap();
+        assert!(sync(&dir, "origin").is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::path::Path;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use chrono::*;
+use ::CliError;
+use standup::{Manager, Change};
+use jsonify;
+
+/// Once the journal grows past this many bytes, the next load or save folds it
+/// into the snapshot, which bounds how much replaying a load has to do.
+pub const COMPACT_AFTER_BYTES: u64 = 512 * 1024;
+
+/// Appends the changes to the journal in a single write, one line each.
+pub fn append(path: &Path, changes: &[Change], message: &str) -> Result<(), CliError> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let at = Local::now().to_rfc3339();
+    let mut lines = String::new();
+    for change in changes {
+        lines.push_str(&jsonify::serialize_change(change, message, &at));
+        lines.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(lines.as_bytes()))
+        .map_err(CliError::Io)
+}
+
+/// Applies every change in the journal to the manager, returning how many
+/// were replayed. A last line without a newline is the remains of an
+/// interrupted append and is skipped.
+pub fn replay(path: &Path, manager: &mut Manager) -> Result<usize, CliError> {
+    if !path.is_file() {
+        return Ok(0);
+    }
+    let mut buf = String::new();
+    try!(fs::File::open(path)
+         .and_then(|mut file| file.read_to_string(&mut buf))
+         .map_err(CliError::Io));
+    let complete = if buf.ends_with("\n") { buf.as_str() } else {
+        buf.rfind('\n').map_or("", |index| &buf[..index + 1])
+    };
+    let mut count = 0;
+    for line in complete.lines().filter(|line| !line.trim().is_empty()) {
+        if let Some(change) = try!(jsonify::deserialize_change(line).map_err(CliError::Parse)) {
+            manager.apply(change);
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+pub fn needs_compaction(path: &Path) -> bool {
+    fs::metadata(path).map(|meta| meta.len() >= COMPACT_AFTER_BYTES).unwrap_or(false)
+}
+
+/// Writes the full state to the snapshot and empties the journal. The
+/// snapshot is written next to the old one and renamed over it, and replaying
+/// a journal onto a newer snapshot is harmless, so a crash part way through
+/// never loses anything.
+pub fn compact(snapshot: &Path, journal: &Path, manager: &Manager) -> Result<(), CliError> {
+    let temp = snapshot.with_extension("json.tmp");
+    try!(fs::File::create(&temp).map_err(CliError::Io).and_then(|file| manager.flush(file)));
+    try!(fs::rename(&temp, snapshot).map_err(CliError::Io));
+    fs::File::create(journal).map(|_| ()).map_err(CliError::Io)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use store::Store;
+    use store::test::scratch;
+    use standup::{Standup, Aspect, Manager};
+    use std::fs;
+    use std::io::Write;
+
+    #[test]
+    fn it_can_replay_appended_changes() {
+        let journal = scratch("journal-replay").join("standup.journal");
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "today");
+        append(&journal, &[Change::Put(standup.clone())], "Add today entry").unwrap();
+        append(&journal, &[Change::Put(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 2).unwrap()))], "").unwrap();
+        append(&journal, &[Change::Delete(NaiveDate::from_ymd_opt(2015, 1, 2).unwrap(), None)], "").unwrap();
+
+        let mut manager = Manager::new();
+        assert_eq!(replay(&journal, &mut manager).unwrap(), 3);
+        assert_eq!(manager.standups(), vec![&standup]);
+    }
+
+    #[test]
+    fn it_will_skip_an_interrupted_append() {
+        let journal = scratch("journal-torn").join("standup.journal");
+        append(&journal, &[Change::Put(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()))], "").unwrap();
+        fs::OpenOptions::new().append(true).open(&journal).unwrap()
+            .write_all(b"{\"op\":\"put\",\"stan").unwrap();
+
+        let mut manager = Manager::new();
+        assert_eq!(replay(&journal, &mut manager).unwrap(), 1);
+    }
+
+    #[test]
+    fn it_can_compact_into_the_snapshot() {
+        let dir = scratch("journal-compact");
+        let store = Store::Journal(dir.join("standup.json"), dir.join("standup.journal"));
+        let mut manager = store.load().unwrap();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Blocker, "blocker"));
+        store.save(&mut manager, "Add blocker entry").unwrap();
+
+        compact(&dir.join("standup.json"), &dir.join("standup.journal"), &manager).unwrap();
+        assert_eq!(fs::metadata(dir.join("standup.journal")).unwrap().len(), 0);
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().blocker, vec!["blocker"]);
+    }
+
+    #[test]
+    fn it_will_compact_a_long_journal_when_loading() {
+        let dir = scratch("journal-compact-on-load");
+        let journal = dir.join("standup.journal");
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "today");
+        let changes: Vec<Change> = (0..COMPACT_AFTER_BYTES / 64).map(|_| Change::Put(standup.clone())).collect();
+        append(&journal, &changes, "Add today entry").unwrap();
+        assert!(needs_compaction(&journal));
+
+        let store = Store::Journal(dir.join("standup.json"), journal.clone());
+        assert_eq!(store.load().unwrap().standups(), vec![&standup]);
+        assert_eq!(fs::metadata(&journal).unwrap().len(), 0);
+        assert_eq!(store.load().unwrap().standups(), vec![&standup]);
+    }
+}
@@ -0,0 +1,491 @@
+pub mod git;
+mod journal;
+mod backup;
+
+use std::path::{Path, PathBuf};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::{Read, Write};
+use chrono::*;
+use ::CliError;
+use ::StandupError;
+use standup::{Standup, Manager};
+use jsonify;
+use crypto;
+use crypto::Secret;
+
+pub use self::backup::{Backups, Retention, summarize, format_id};
+
+///
+/// ## Store
+///
+/// Where the standups live on disk. Either everything is kept in a single
+/// JSON file, that file is encrypted, the file is a snapshot with a journal
+/// of changes appended after it, or each day gets its own pretty-printed
+/// file inside a git repository so that it can be synced between machines.
+///
+pub enum Store {
+    File(PathBuf),
+    Encrypted(PathBuf, Secret),
+    Journal(PathBuf, PathBuf),
+    Git(PathBuf),
+}
+
+impl Store {
+    /// Uses the git repository next to the data file when sync has been set
+    /// up, then the journal if journaling is on, falling back to the data file
+    /// itself. An encrypted file asks for its passphrase here.
+    pub fn locate(path: &Path) -> Result<Store, CliError> {
+        let dir = Store::git_dir(path);
+        let journal = Store::journal_path(path);
+        if git::is_repo(&dir) {
+            Ok(Store::Git(dir))
+        } else if journal.is_file() {
+            Ok(Store::Journal(path.to_path_buf(), journal))
+        } else if try!(Store::is_encrypted(path)) {
+            Secret::obtain(false).map(|secret| Store::Encrypted(path.to_path_buf(), secret))
+        } else {
+            Ok(Store::File(path.to_path_buf()))
+        }
+    }
+
+    /// Whether `locate` would need the passphrase or key file, because the
+    /// standups are kept in an encrypted file.
+    pub fn is_locked(path: &Path) -> Result<bool, CliError> {
+        if git::is_repo(&Store::git_dir(path)) || Store::journal_path(path).is_file() {
+            return Ok(false);
+        }
+        Store::is_encrypted(path)
+    }
+
+    fn is_encrypted(path: &Path) -> Result<bool, CliError> {
+        if !path.is_file() {
+            return Ok(false);
+        }
+        let mut header = vec![];
+        try!(fs::File::open(path)
+             .and_then(|file| file.take(64).read_to_end(&mut header))
+             .map_err(CliError::Io));
+        Ok(crypto::is_encrypted(&header))
+    }
+
+    /// `~/.standup.json` keeps its journal in `~/.standup.journal`.
+    pub fn journal_path(path: &Path) -> PathBuf {
+        path.with_extension("journal")
+    }
+
+    pub fn backups_dir(path: &Path) -> PathBuf {
+        path.with_extension("backups")
+    }
+
+    /// `~/.standup.json` syncs through the repository in `~/.standup`.
+    pub fn git_dir(path: &Path) -> PathBuf {
+        let dir = path.with_extension("");
+        if dir == path { path.with_extension("d") } else { dir }
+    }
+
+    pub fn load(&self) -> Result<Manager, CliError> {
+        match *self {
+            Store::File(ref path) => Store::load_file(path),
+            Store::Encrypted(ref path, ref secret) => {
+                let mut bytes = vec![];
+                try!(fs::File::open(path)
+                     .and_then(|mut file| file.read_to_end(&mut bytes))
+                     .map_err(CliError::Io));
+                let plaintext = try!(crypto::decrypt(secret, &bytes));
+                Manager::from_reader(plaintext.as_slice())
+            },
+            Store::Journal(ref snapshot, ref journal) => {
+                let mut manager = try!(Store::load_file(snapshot));
+                try!(journal::replay(journal, &mut manager));
+                // A journal can pass the limit without a save noticing, such as
+                // one copied over from another machine, so it is checked here too.
+                if journal::needs_compaction(journal) {
+                    try!(journal::compact(snapshot, journal, &manager));
+                }
+                Ok(manager)
+            },
+            Store::Git(ref dir) => Store::load_days(dir),
+        }
+    }
+
+    /// The single file that holds everything, when there is one. This is what
+    /// gets backed up; the journal and git already keep their own history.
+    pub fn data_file(&self) -> Option<&Path> {
+        match *self {
+            Store::File(ref path) | Store::Encrypted(ref path, _) => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Reads the contents of a data file, such as a backup, decrypting it
+    /// first if it needs to be.
+    pub fn decode(&self, bytes: &[u8]) -> Result<Manager, CliError> {
+        if crypto::is_encrypted(bytes) {
+            let secret = match *self {
+                Store::Encrypted(_, ref secret) => secret.clone(),
+                _ => try!(Secret::obtain(false)),
+            };
+            let plaintext = try!(crypto::decrypt(&secret, bytes));
+            Manager::from_reader(plaintext.as_slice())
+        } else {
+            Manager::from_reader(bytes)
+        }
+    }
+
+    /// Writes the manager out. The message describes the change and becomes
+    /// the commit message when the store is backed by git, or is recorded
+    /// alongside each change in the journal.
+    pub fn save(&self, manager: &mut Manager, message: &str) -> Result<(), CliError> {
+        let changes = manager.take_changes();
+        match *self {
+            Store::File(ref path) => Store::save_file(path, manager),
+            Store::Encrypted(ref path, ref secret) => Store::save_encrypted(path, secret, manager),
+            Store::Journal(ref snapshot, ref journal) => {
+                try!(journal::append(journal, &changes, message));
+                if journal::needs_compaction(journal) {
+                    journal::compact(snapshot, journal, manager)
+                } else {
+                    Ok(())
+                }
+            },
+            Store::Git(ref dir) => {
+                try!(Store::save_days(dir, manager));
+                git::commit(dir, message)
+            },
+        }
+    }
+
+    /// Turns the directory next to the data file into a git repository,
+    /// cloning the remote under the given name if one is given, and moves the
+    /// standups from the single file into it.
+    pub fn enable_sync(path: &Path, manager: &Manager, url: Option<&str>, remote: &str) -> Result<Store, CliError> {
+        let dir = Store::git_dir(path);
+        if git::is_repo(&dir) {
+            return Err(CliError::Cli(StandupError::SyncAlreadyEnabled));
+        }
+        try!(git::init(&dir, url, remote));
+        let store = Store::Git(dir);
+        let mut merged = try!(store.load());
+        merged.merge(manager.clone());
+        try!(store.save(&mut merged, "Import standups"));
+        Ok(store)
+    }
+
+    /// Pulls and pushes against the named remote of the git repository.
+    pub fn sync(&self, remote: &str) -> Result<(), CliError> {
+        match *self {
+            Store::Git(ref dir) => git::sync(dir, remote),
+            _ => Err(CliError::Cli(StandupError::SyncNotEnabled)),
+        }
+    }
+
+    /// Switches the single file over to a snapshot and journal. The current
+    /// file becomes the snapshot.
+    pub fn enable_journal(&self) -> Result<Store, CliError> {
+        match *self {
+            Store::File(ref path) => {
+                let journal = Store::journal_path(path);
+                try!(fs::File::create(&journal).map_err(CliError::Io));
+                Ok(Store::Journal(path.clone(), journal))
+            },
+            Store::Journal(..) => Err(CliError::Cli(StandupError::JournalAlreadyEnabled)),
+            _ => Err(CliError::Cli(StandupError::JournalNotSupported)),
+        }
+    }
+
+    /// Folds the journal into the snapshot and goes back to a single file.
+    pub fn disable_journal(&self, manager: &Manager) -> Result<Store, CliError> {
+        match *self {
+            Store::Journal(ref snapshot, ref journal) => {
+                try!(journal::compact(snapshot, journal, manager));
+                try!(fs::remove_file(journal).map_err(CliError::Io));
+                Ok(Store::File(snapshot.clone()))
+            },
+            _ => Err(CliError::Cli(StandupError::JournalNotEnabled)),
+        }
+    }
+
+    /// Folds the journal into the snapshot now rather than waiting for it to
+    /// grow large enough.
+    pub fn compact(&self, manager: &Manager) -> Result<(), CliError> {
+        match *self {
+            Store::Journal(ref snapshot, ref journal) => journal::compact(snapshot, journal, manager),
+            _ => Err(CliError::Cli(StandupError::JournalNotEnabled)),
+        }
+    }
+
+    /// Rewrites the single file encrypted.
+    pub fn encrypt(&self, manager: &Manager, secret: Secret) -> Result<Store, CliError> {
+        match *self {
+            Store::File(ref path) => {
+                try!(Store::save_encrypted(path, &secret, manager));
+                Ok(Store::Encrypted(path.clone(), secret))
+            },
+            Store::Encrypted(..) => Err(CliError::Cli(StandupError::AlreadyEncrypted)),
+            _ => Err(CliError::Cli(StandupError::EncryptionNotSupported)),
+        }
+    }
+
+    /// Rewrites an encrypted file as plain JSON.
+    pub fn decrypt(&self, manager: &Manager) -> Result<Store, CliError> {
+        match *self {
+            Store::Encrypted(ref path, _) => {
+                try!(Store::save_file(path, manager));
+                Ok(Store::File(path.clone()))
+            },
+            _ => Err(CliError::Cli(StandupError::NotEncrypted)),
+        }
+    }
+
+    /// Writes next to the file and renames it over, so that an interrupted
+    /// write never leaves half a ciphertext behind.
+    fn save_encrypted(path: &Path, secret: &Secret, manager: &Manager) -> Result<(), CliError> {
+        let mut plaintext: Vec<u8> = Vec::new();
+        try!(manager.flush(&mut plaintext));
+        let bytes = try!(crypto::encrypt(secret, &plaintext));
+        let temp = path.with_extension("json.tmp");
+        try!(fs::File::create(&temp)
+             .and_then(|mut file| file.write_all(&bytes))
+             .map_err(CliError::Io));
+        fs::rename(&temp, path).map_err(CliError::Io)
+    }
+
+    fn save_file(path: &Path, manager: &Manager) -> Result<(), CliError> {
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(path);
+        file.map_err(CliError::Io).and_then(|file| manager.flush(file))
+    }
+
+    fn load_file(path: &Path) -> Result<Manager, CliError> {
+        if path.is_file() {
+            OpenOptions::new()
+                .read(true)
+                .open(path)
+                .map_err(CliError::Io)
+                .and_then(Manager::from_reader)
+        } else {
+            OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(path)
+                .map_err(CliError::Io)
+                .map(|_| Manager::new())
+        }
+    }
+
+    fn load_days(dir: &Path) -> Result<Manager, CliError> {
+        let mut manager = Manager::new();
+        for path in try!(Store::day_files(dir)) {
+            let mut buf = String::new();
+            try!(fs::File::open(&path)
+                 .and_then(|mut file| file.read_to_string(&mut buf))
+                 .map_err(CliError::Io));
+            manager.insert(try!(jsonify::deserialize_day(buf).map_err(CliError::Parse)));
+        }
+        Ok(manager)
+    }
+
+    fn save_days(dir: &Path, manager: &Manager) -> Result<(), CliError> {
+        let mut kept = vec![];
+        for standup in manager.standups() {
+            let path = dir.join(Store::day_file_name(standup));
+            let json = jsonify::serialize_day(standup);
+            let unchanged = fs::File::open(&path).ok().map_or(false, |mut file| {
+                let mut existing = String::new();
+                file.read_to_string(&mut existing).is_ok() && existing == json
+            });
+            if !unchanged {
+                try!(fs::File::create(&path)
+                     .and_then(|mut file| file.write_all(json.as_bytes()))
+                     .map_err(CliError::Io));
+            }
+            kept.push(path);
+        }
+        for path in try!(Store::day_files(dir)) {
+            if !kept.contains(&path) {
+                try!(fs::remove_file(&path).map_err(CliError::Io));
+            }
+        }
+        Ok(())
+    }
+
+    /// `YYYY-MM-DD.json`, or `YYYY-MM-DD.name.json` for a standup with an
+    /// author, with anything but letters, digits, `-` and `_` in the name
+    /// percent-encoded, so that no two names share a file. The author itself
+    /// is kept inside the file.
+    fn day_file_name(standup: &Standup) -> String {
+        match standup.author {
+            Some(ref author) => {
+                let mut name = String::new();
+                for c in author.chars() {
+                    if c.is_alphanumeric() || c == '-' || c == '_' {
+                        name.push(c);
+                    } else {
+                        let mut bytes = [0; 4];
+                        for byte in c.encode_utf8(&mut bytes).bytes() {
+                            name.push_str(&format!("%{:02X}", byte));
+                        }
+                    }
+                }
+                format!("{}.{}.json", standup.date.format("%F"), name)
+            },
+            None => format!("{}.json", standup.date.format("%F")),
+        }
+    }
+
+    /// Lists the day files in the directory, ignoring anything else that might
+    /// be committed alongside them.
+    fn day_files(dir: &Path) -> Result<Vec<PathBuf>, CliError> {
+        let entries = try!(fs::read_dir(dir).map_err(CliError::Io));
+        let mut paths = vec![];
+        for entry in entries {
+            let path = try!(entry.map_err(CliError::Io)).path();
+            let is_day = path.extension().map_or(false, |ext| ext == "json") &&
+                path.file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.split('.').next())
+                    .map_or(false, |day| NaiveDate::parse_from_str(day, "%F").is_ok());
+            if is_day { paths.push(path); }
+        }
+        paths.sort();
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use super::git;
+    use std::env;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::process;
+    use standup::{Standup, Aspect, Manager};
+
+    /// Creates an empty directory under the system temp dir for a test.
+    pub fn scratch(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("standup-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn it_can_save_and_load_a_file() {
+        let store = Store::File(scratch("store-file").join("standup.json"));
+        let mut manager = store.load().unwrap();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "today"));
+        store.save(&mut manager, "Add today entry").unwrap();
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().today, vec!["today"]);
+    }
+
+    #[test]
+    fn it_will_write_a_file_per_day() {
+        let dir = scratch("store-days");
+        let mut manager = Manager::new();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()));
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 2).unwrap()));
+        Store::save_days(&dir, &manager).unwrap();
+        assert!(dir.join("2015-01-01.json").is_file());
+        assert!(dir.join("2015-01-02.json").is_file());
+
+        manager.delete_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None);
+        Store::save_days(&dir, &manager).unwrap();
+        assert!(!dir.join("2015-01-01.json").exists());
+        assert_eq!(Store::load_days(&dir).unwrap().standups().len(), 1);
+    }
+
+    #[test]
+    fn it_will_write_a_file_per_person_per_day() {
+        let dir = scratch("store-people");
+        let mut manager = Manager::new();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).set_author(Some("Ann Lee".to_string())));
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).set_author(Some("bob".to_string())));
+        Store::save_days(&dir, &manager).unwrap();
+        assert!(dir.join("2015-01-01.Ann%20Lee.json").is_file());
+        let loaded = Store::load_days(&dir).unwrap();
+        assert_eq!(loaded.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), Some("Ann Lee")).unwrap().author,
+                   Some("Ann Lee".to_string()));
+        assert_eq!(loaded.standups().len(), 2);
+    }
+
+    #[test]
+    fn it_will_keep_similar_names_apart() {
+        let dir = scratch("store-similar-people");
+        let names = ["Ann Lee", "Ann_Lee", "Ann.Lee", "Ann%2ELee"];
+        let mut manager = Manager::new();
+        for name in names.iter() {
+            manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap())
+                           .set_author(Some(name.to_string()))
+                           .add(Aspect::Today, name));
+        }
+        Store::save_days(&dir, &manager).unwrap();
+        let loaded = Store::load_days(&dir).unwrap();
+        assert_eq!(loaded.standups().len(), names.len());
+        for name in names.iter() {
+            let standup = loaded.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), Some(name)).unwrap();
+            assert_eq!(standup.today, vec![name.to_string()]);
+        }
+    }
+
+    #[test]
+    fn it_will_commit_every_save_when_backed_by_git() {
+        let dir = scratch("store-git").join("data");
+        git::init(&dir, None, "origin").unwrap();
+        let store = Store::Git(dir.clone());
+        let mut manager = Manager::new();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "today"));
+        store.save(&mut manager, "Add today entry for 2015-01-01").unwrap();
+        let log = git::run(&dir, &["log", "--format=%s"]).unwrap();
+        assert_eq!(log.trim(), "Add today entry for 2015-01-01");
+    }
+
+    #[test]
+    fn it_will_only_append_changes_when_journaling() {
+        let dir = scratch("store-journal");
+        let store = Store::Journal(dir.join("standup.json"), dir.join("standup.journal"));
+        let mut manager = store.load().unwrap();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "today"));
+        store.save(&mut manager, "Add today entry").unwrap();
+        assert_eq!(fs::metadata(dir.join("standup.json")).unwrap().len(), 0);
+
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().today, vec!["today"]);
+    }
+
+    #[test]
+    fn it_can_encrypt_and_decrypt_the_file() {
+        let path = scratch("store-encrypted").join("standup.json");
+        let store = Store::File(path.clone());
+        let mut manager = store.load().unwrap();
+        manager.insert(Standup::from_date(NaiveDate::from_ymd_opt(2015, 1, 1).unwrap()).add(Aspect::Today, "incident"));
+        store.save(&mut manager, "").unwrap();
+
+        let store = store.encrypt(&manager, Secret::new(b"passphrase".to_vec())).unwrap();
+        assert!(Store::is_encrypted(&path).unwrap());
+        assert_eq!(store.load().unwrap().get_by(&NaiveDate::from_ymd_opt(2015, 1, 1).unwrap(), None).unwrap().today, vec!["incident"]);
+
+        let wrong = Store::Encrypted(path.clone(), Secret::new(b"wrong".to_vec()));
+        assert!(wrong.load().is_err());
+
+        let store = store.decrypt(&manager).unwrap();
+        assert!(!Store::is_encrypted(&path).unwrap());
+        assert_eq!(store.load().unwrap().standups().len(), 1);
+    }
+
+    #[test]
+    fn it_will_keep_everything_next_to_the_data_file() {
+        let path = PathBuf::from("/data/standup.json");
+        assert_eq!(Store::journal_path(&path), PathBuf::from("/data/standup.journal"));
+        assert_eq!(Store::backups_dir(&path), PathBuf::from("/data/standup.backups"));
+        assert_eq!(Store::git_dir(&path), PathBuf::from("/data/standup"));
+        assert_eq!(Store::git_dir(&PathBuf::from("/data/standup")), PathBuf::from("/data/standup.d"));
+    }
+
+    #[test]
+    fn it_will_refuse_to_sync_a_single_file() {
+        let store = Store::File(scratch("store-no-sync").join("standup.json"));
+        assert!(store.sync("origin").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use std::env;
+use std::io::{self, IsTerminal};
+use crossterm::terminal;
+use standup::{Standup, Aspect, Layout};
+
+pub static COLORS: &'static [&'static str] = &["always", "never", "auto"];
+
+/// Whether to colour the output, from `--color`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Color {
+    Always,
+    Never,
+    /// Colours only when stdout is a terminal and `NO_COLOR` is not set.
+    Auto,
+}
+
+impl Color {
+    pub fn from_name(name: &str) -> Option<Color> {
+        match name {
+            "always" => Some(Color::Always),
+            "never" => Some(Color::Never),
+            "auto" => Some(Color::Auto),
+            _ => None
+        }
+    }
+}
+
+const RESET: &'static str = "\x1b[0m";
+const BOLD: &'static str = "\x1b[1m";
+const DIM: &'static str = "\x1b[2m";
+const RED: &'static str = "\x1b[31m";
+const GREEN: &'static str = "\x1b[32m";
+const BLUE: &'static str = "\x1b[34m";
+
+///
+/// ## Terminal
+///
+/// Prints standups laid out the same way as `Layout`, but with a colour per
+/// aspect and long entries wrapped to the width of the terminal, lining up
+/// under the start of the entry. Without colour or a width the output is
+/// exactly `Layout`'s.
+///
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Terminal {
+    pub color: bool,
+    pub width: Option<usize>,
+}
+
+impl Terminal {
+    /// Looks at stdout to decide on colour and width. Piped output is never
+    /// wrapped.
+    pub fn detect(color: Color) -> Terminal {
+        let is_terminal = io::stdout().is_terminal();
+        let no_color = env::var("NO_COLOR").map(|value| !value.is_empty()).unwrap_or(false);
+        Terminal {
+            color: match color {
+                Color::Always => true,
+                Color::Never => false,
+                Color::Auto => is_terminal && !no_color,
+            },
+            width: if is_terminal { terminal::size().ok().map(|(columns, _)| columns as usize) } else { None },
+        }
+    }
+
+    pub fn render(&self, layout: &Layout, standup: &Standup) -> String {
+        self.render_day(layout, &[standup])
+    }
+
+    /// Renders a day like `Layout::render_day`.
+    pub fn render_day(&self, layout: &Layout, standups: &[&Standup]) -> String {
+        let first = match standups.first() {
+            Some(first) => first,
+            None => return String::new(),
+        };
+        let mut out = format!("{}\n", self.paint(DIM, &first.date.format(&layout.date_format).to_string()));
+        if standups.len() == 1 && first.author.is_none() {
+            out.push_str(&self.render_aspects(layout, first, "  "));
+            return out;
+        }
+        for standup in standups {
+            let author = standup.author.as_ref().map_or("(no author)", |author| author.as_str());
+            out.push_str(&format!("  {}:\n", self.paint(BOLD, author)));
+            out.push_str(&self.render_aspects(layout, standup, "    "));
+        }
+        out
+    }
+
+    fn render_aspects(&self, layout: &Layout, standup: &Standup, indent: &str) -> String {
+        let mut out = String::new();
+        for &aspect in layout.order.iter() {
+            let messages = standup.messages(aspect);
+            if messages.is_empty() && layout.hide_empty.contains(&aspect) {
+                continue;
+            }
+            out.push_str(&format!("{}{}\n", indent, self.paint(color_of(aspect), &format!("{}:", aspect))));
+            for (i, message) in messages.iter().enumerate() {
+                let number = format!("{}  {}. ", indent, i + 1);
+                let hanging = " ".repeat(number.chars().count());
+                let lines = match self.width {
+                    Some(width) if width > hanging.len() => wrap(message, width - hanging.len()),
+                    _ => vec![message.to_string()],
+                };
+                for (j, line) in lines.iter().enumerate() {
+                    let line = if aspect == Aspect::Blocker { self.paint(RED, line) } else { line.clone() };
+                    out.push_str(&format!("{}{}\n", if j == 0 { &number } else { &hanging }, line));
+                }
+            }
+        }
+        out
+    }
+
+    fn paint(&self, code: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+fn color_of(aspect: Aspect) -> &'static str {
+    match aspect {
+        Aspect::Today => GREEN,
+        Aspect::Yesterday => BLUE,
+        Aspect::Blocker => RED,
+    }
+}
+
+/// Breaks text into lines of at most `width` characters at spaces. A word
+/// longer than the width gets a line of its own rather than being split.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(line);
+            line = String::new();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::*;
+    use standup::{Standup, Aspect, Layout};
+
+    fn standup() -> Standup {
+        Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap())
+            .add(Aspect::Today, "review the migration before it goes out to everyone")
+            .add(Aspect::Blocker, "waiting on ops")
+    }
+
+    #[test]
+    fn it_will_match_the_layout_when_plain() {
+        let alice = standup().set_author(Some("alice".to_string()));
+        let bob = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap()).set_author(Some("bob".to_string()));
+        let layout = Layout::default();
+        let plain = Terminal { color: false, width: None };
+        assert_eq!(plain.render(&layout, &standup()), layout.render(&standup()));
+        assert_eq!(plain.render_day(&layout, &[&alice, &bob]), layout.render_day(&[&alice, &bob]));
+    }
+
+    #[test]
+    fn it_will_colour_each_aspect() {
+        let terminal = Terminal { color: true, width: None };
+        assert_eq!(terminal.render(&Layout::default(), &standup()), concat!(
+            "\x1b[2m2016-03-07 - Monday\x1b[0m\n",
+            "  \x1b[32mtoday:\x1b[0m\n",
+            "    1. review the migration before it goes out to everyone\n",
+            "  \x1b[34myesterday:\x1b[0m\n",
+            "  \x1b[31mblocker:\x1b[0m\n",
+            "    1. \x1b[31mwaiting on ops\x1b[0m\n"));
+    }
+
+    #[test]
+    fn it_will_wrap_long_entries_with_a_hanging_indent() {
+        let terminal = Terminal { color: false, width: Some(30) };
+        assert_eq!(terminal.render(&Layout::default(), &standup()), concat!(
+            "2016-03-07 - Monday\n",
+            "  today:\n",
+            "    1. review the migration\n",
+            "       before it goes out to\n",
+            "       everyone\n",
+            "  yesterday:\n",
+            "  blocker:\n",
+            "    1. waiting on ops\n"));
+    }
+
+    #[test]
+    fn it_will_not_split_long_words() {
+        assert_eq!(wrap("see https://example.com/a/very/long/link ok", 10),
+                   vec!["see", "https://example.com/a/very/long/link", "ok"]);
+        assert_eq!(wrap("", 10), vec![""]);
+    }
+}
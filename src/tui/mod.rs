@@ -0,0 +1,122 @@
+mod state;
+
+use std::io;
+use std::io::Stdout;
+use crossterm::ExecutableCommand;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{enable_raw_mode, disable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::{Frame, Terminal};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Layout, Constraint, Direction};
+use ratatui::style::{Style, Color, Modifier};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ::CliError;
+use app::App;
+use standup::Aspect;
+
+pub use self::state::{State, Key, Focus, Mode};
+
+static HELP: &'static str =
+    "tab switch pane  t/y/b add  e edit  d delete  J/K reorder  </> move aspect  / search  q quit";
+
+/// Opens the TUI on the app's date and runs it until it is closed. The
+/// terminal is put back the way it was even when something fails.
+pub fn run(app: App) -> Result<(), CliError> {
+    try!(enable_raw_mode().map_err(CliError::Io));
+    let mut stdout = io::stdout();
+    if let Err(err) = stdout.execute(EnterAlternateScreen) {
+        let _ = disable_raw_mode();
+        return Err(CliError::Io(err));
+    }
+    let result = Terminal::new(CrosstermBackend::new(stdout))
+        .map_err(CliError::Io)
+        .and_then(|mut terminal| event_loop(&mut terminal, State::new(app)));
+    let _ = io::stdout().execute(LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+    result
+}
+
+fn event_loop(terminal: &mut Terminal<CrosstermBackend<Stdout>>, mut state: State) -> Result<(), CliError> {
+    loop {
+        try!(terminal.draw(|frame| draw(frame, &state)).map_err(CliError::Io));
+        let key = match try!(event::read().map_err(CliError::Io)) {
+            Event::Key(event) if event.kind == KeyEventKind::Press => {
+                match event.code {
+                    KeyCode::Char(c) => Key::Char(c),
+                    KeyCode::Up => Key::Up,
+                    KeyCode::Down => Key::Down,
+                    KeyCode::Enter => Key::Enter,
+                    KeyCode::Esc => Key::Esc,
+                    KeyCode::Backspace => Key::Backspace,
+                    KeyCode::Tab => Key::Tab,
+                    _ => continue,
+                }
+            },
+            _ => continue,
+        };
+        if !state.handle(key) {
+            return Ok(());
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, state: &State) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1), Constraint::Length(1)])
+        .split(frame.size());
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(16), Constraint::Min(20)])
+        .split(rows[0]);
+
+    let highlight = |focus: Focus| {
+        if state.focus == focus {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        }
+    };
+
+    let title = if state.search.is_empty() { "Days".to_string() } else { format!("Days /{}", state.search) };
+    let dates: Vec<ListItem> = state.dates().iter()
+        .map(|date| ListItem::new(date.format("%F %a").to_string()))
+        .collect();
+    let mut selected = ListState::default().with_selected(Some(state.selected_date));
+    frame.render_stateful_widget(List::new(dates)
+                                     .block(Block::default().borders(Borders::ALL).title(title))
+                                     .highlight_style(highlight(Focus::Dates)),
+                                 panes[0], &mut selected);
+
+    let standup = state.standup();
+    let mut items = vec![];
+    let mut selected_item = None;
+    let mut entry = 0;
+    for aspect in Aspect::all() {
+        items.push(ListItem::new(Line::from(Span::styled(aspect.to_string(), Style::default().add_modifier(Modifier::UNDERLINED)))));
+        for (i, message) in standup.messages(aspect).iter().enumerate() {
+            let style = if aspect == Aspect::Blocker { Style::default().fg(Color::Red) } else { Style::default() };
+            if entry == state.selected_entry {
+                selected_item = Some(items.len());
+            }
+            items.push(ListItem::new(Line::from(Span::styled(format!("  {}. {}", i + 1, message), style))));
+            entry += 1;
+        }
+    }
+    let title = standup.date.format(&state.app().layout().date_format).to_string();
+    let mut selected = ListState::default().with_selected(selected_item);
+    frame.render_stateful_widget(List::new(items)
+                                     .block(Block::default().borders(Borders::ALL).title(title))
+                                     .highlight_style(highlight(Focus::Entries)),
+                                 panes[1], &mut selected);
+
+    let prompt = match state.mode {
+        Mode::Normal => state.status.clone(),
+        Mode::Add(aspect, ref text) => format!("add {}: {}_", aspect, text),
+        Mode::Edit(aspect, index, ref text) => format!("edit {} {}: {}_", aspect, index + 1, text),
+        Mode::Search(ref text) => format!("/{}_", text),
+    };
+    frame.render_widget(Paragraph::new(prompt), rows[1]);
+    frame.render_widget(Paragraph::new(HELP).style(Style::default().fg(Color::DarkGray)), rows[2]);
+}
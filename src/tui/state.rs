@@ -0,0 +1,352 @@
+use chrono::*;
+use app::App;
+use standup::{Standup, Aspect};
+
+/// The keys the TUI understands, apart from how the terminal reports them.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Enter,
+    Esc,
+    Backspace,
+    Tab,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Focus {
+    Dates,
+    Entries,
+}
+
+/// What typed text is for.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Normal,
+    Add(Aspect, String),
+    Edit(Aspect, usize, String),
+    Search(String),
+}
+
+///
+/// ## State
+///
+/// Everything the TUI shows and how keys change it, kept apart from drawing so
+/// it can be tested without a terminal. Changes go through the `App`, so they
+/// are written out exactly as the other subcommands write them.
+///
+pub struct State {
+    app: App,
+    dates: Vec<NaiveDate>,
+    today: NaiveDate,
+    pub selected_date: usize,
+    pub selected_entry: usize,
+    pub focus: Focus,
+    pub mode: Mode,
+    pub search: String,
+    pub status: String,
+}
+
+impl State {
+    pub fn new(app: App) -> State {
+        let today = app.date();
+        let mut state = State {
+            app: app,
+            dates: vec![],
+            today: today,
+            selected_date: 0,
+            selected_entry: 0,
+            focus: Focus::Dates,
+            mode: Mode::Normal,
+            search: String::new(),
+            status: String::new(),
+        };
+        state.refresh();
+        state
+    }
+
+    pub fn app(&self) -> &App {
+        &self.app
+    }
+
+    /// The days to pick from, newest first. Without a search that is every
+    /// day with a standup, and the day the TUI was opened on.
+    pub fn dates(&self) -> &[NaiveDate] {
+        &self.dates
+    }
+
+    pub fn standup(&self) -> Standup {
+        self.app.get_standup()
+    }
+
+    /// The entries of the selected standup in the order they are shown.
+    pub fn entries(&self) -> Vec<(Aspect, usize, String)> {
+        let standup = self.standup();
+        Aspect::all().into_iter()
+            .flat_map(|aspect| {
+                standup.messages(aspect).iter().enumerate()
+                    .map(|(index, message)| (aspect, index, message.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Handles a key press, returning false when the TUI should close.
+    pub fn handle(&mut self, key: Key) -> bool {
+        let mode = self.mode.clone();
+        match mode {
+            Mode::Normal => return self.handle_normal(key),
+            Mode::Add(aspect, text) => {
+                if let Some(text) = self.type_into(key, text, |text| Mode::Add(aspect, text)) {
+                    if !text.trim().is_empty() {
+                        let result = self.app.record(aspect, text.trim().to_string());
+                        self.report(result, "Added");
+                    }
+                }
+            },
+            Mode::Edit(aspect, index, text) => {
+                if let Some(text) = self.type_into(key, text, |text| Mode::Edit(aspect, index, text)) {
+                    if !text.trim().is_empty() {
+                        let result = self.app.edit_line(aspect, index, text.trim().to_string());
+                        self.report(result, "Changed");
+                    }
+                }
+            },
+            Mode::Search(text) => {
+                match key {
+                    Key::Esc => {
+                        self.mode = Mode::Normal;
+                        self.search.clear();
+                    },
+                    Key::Enter => self.mode = Mode::Normal,
+                    _ => {
+                        let text = edit_text(key, text);
+                        self.search = text.clone();
+                        self.mode = Mode::Search(text);
+                    },
+                }
+                self.selected_date = 0;
+            },
+        }
+        self.refresh();
+        true
+    }
+
+    fn handle_normal(&mut self, key: Key) -> bool {
+        self.status.clear();
+        let selected = self.entries().get(self.selected_entry).cloned();
+        match key {
+            Key::Char('q') | Key::Esc => return false,
+            Key::Tab => {
+                self.focus = if self.focus == Focus::Dates { Focus::Entries } else { Focus::Dates };
+            },
+            Key::Up | Key::Char('k') => self.step(-1),
+            Key::Down | Key::Char('j') => self.step(1),
+            Key::Char('/') => self.mode = Mode::Search(self.search.clone()),
+            Key::Char('t') => self.mode = Mode::Add(Aspect::Today, String::new()),
+            Key::Char('y') => self.mode = Mode::Add(Aspect::Yesterday, String::new()),
+            Key::Char('b') => self.mode = Mode::Add(Aspect::Blocker, String::new()),
+            Key::Char('e') | Key::Enter => {
+                if let Some((aspect, index, message)) = selected {
+                    self.focus = Focus::Entries;
+                    self.mode = Mode::Edit(aspect, index, message);
+                }
+            },
+            Key::Char('d') => {
+                if let Some((aspect, index, _)) = selected {
+                    let result = self.app.delete_line(aspect, index);
+                    self.report(result, "Deleted");
+                }
+            },
+            Key::Char('K') => self.reorder(-1),
+            Key::Char('J') => self.reorder(1),
+            Key::Char('<') => self.move_to_aspect(-1),
+            Key::Char('>') => self.move_to_aspect(1),
+            _ => {},
+        }
+        self.refresh();
+        true
+    }
+
+    /// Moves the selection in the focused pane.
+    fn step(&mut self, by: isize) {
+        match self.focus {
+            Focus::Dates => {
+                self.selected_date = clamp(self.selected_date as isize + by, self.dates.len());
+                self.selected_entry = 0;
+            },
+            Focus::Entries => self.selected_entry = clamp(self.selected_entry as isize + by, self.entries().len()),
+        }
+    }
+
+    /// Swaps the selected entry with its neighbour in the same aspect.
+    fn reorder(&mut self, by: isize) {
+        let (aspect, index, _) = match self.entries().get(self.selected_entry).cloned() {
+            Some(selected) => selected,
+            None => return,
+        };
+        let to = index as isize + by;
+        if to < 0 {
+            return;
+        }
+        let result = self.app.move_line(aspect, index, aspect, Some(to as usize));
+        if result.is_ok() {
+            self.selected_entry = (self.selected_entry as isize + by) as usize;
+        }
+        self.report(result, "Moved");
+    }
+
+    /// Moves the selected entry to the end of the aspect before or after its
+    /// own.
+    fn move_to_aspect(&mut self, by: isize) {
+        let (aspect, index, message) = match self.entries().get(self.selected_entry).cloned() {
+            Some(selected) => selected,
+            None => return,
+        };
+        let aspects = Aspect::all();
+        let position = aspects.iter().position(|a| *a == aspect).unwrap() as isize + by;
+        if position < 0 || position as usize >= aspects.len() {
+            return;
+        }
+        let target = aspects[position as usize];
+        let result = self.app.move_line(aspect, index, target, None);
+        self.report(result, &format!("Moved to {}", target));
+        let entries = self.entries();
+        self.selected_entry = entries.iter()
+            .rposition(|&(a, _, ref m)| a == target && *m == message)
+            .unwrap_or(0);
+    }
+
+    /// Applies a key to the text being typed, returning the text once it is
+    /// entered.
+    fn type_into<F>(&mut self, key: Key, text: String, mode: F) -> Option<String> where F: Fn(String) -> Mode {
+        match key {
+            Key::Esc => {
+                self.mode = Mode::Normal;
+                None
+            },
+            Key::Enter => {
+                self.mode = Mode::Normal;
+                Some(text)
+            },
+            _ => {
+                self.mode = mode(edit_text(key, text));
+                None
+            },
+        }
+    }
+
+    fn report<E: ToString>(&mut self, result: Result<(), E>, done: &str) {
+        self.status = match result {
+            Ok(_) => done.to_string(),
+            Err(err) => format!("error: {}", err.to_string()),
+        };
+    }
+
+    /// Rebuilds the list of days and keeps the selections in range.
+    fn refresh(&mut self) {
+        let search = self.search.to_lowercase();
+        let mut dates: Vec<NaiveDate> = self.app.standups().iter()
+            .filter(|standup| search.is_empty() || Aspect::all().into_iter().any(|aspect| {
+                standup.messages(aspect).iter().any(|message| message.to_lowercase().contains(&search))
+            }))
+            .map(|standup| standup.date)
+            .collect();
+        if search.is_empty() {
+            dates.push(self.today);
+        }
+        dates.sort_by(|a, b| b.cmp(a));
+        dates.dedup();
+        self.dates = dates;
+        self.selected_date = clamp(self.selected_date as isize, self.dates.len());
+        if let Some(&date) = self.dates.get(self.selected_date) {
+            self.app.set_date(date);
+        }
+        self.selected_entry = clamp(self.selected_entry as isize, self.entries().len());
+    }
+}
+
+fn edit_text(key: Key, text: String) -> String {
+    let mut text = text;
+    match key {
+        Key::Char(c) => text.push(c),
+        Key::Backspace => { text.pop(); },
+        _ => {},
+    }
+    text
+}
+
+/// Keeps an index within a list of the given length.
+fn clamp(index: isize, len: usize) -> usize {
+    if index < 0 || len == 0 {
+        0
+    } else if index as usize >= len {
+        len - 1
+    } else {
+        index as usize
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use app::test::app;
+    use standup::Aspect;
+
+    fn press(state: &mut State, keys: &str) {
+        for c in keys.chars() {
+            let key = match c {
+                '\n' => Key::Enter,
+                '\t' => Key::Tab,
+                '\x1b' => Key::Esc,
+                c => Key::Char(c),
+            };
+            state.handle(key);
+        }
+    }
+
+    #[test]
+    fn it_will_write_entries_through_the_app() {
+        let mut state = State::new(app("tui-add", "2016-03-04"));
+        press(&mut state, "treview\nbwaiting\n\tje");
+        for _ in 0.."waiting".len() {
+            state.handle(Key::Backspace);
+        }
+        press(&mut state, "shipped\n");
+        state.app.reload().unwrap();
+        assert_eq!(state.standup().today, vec!["review"]);
+        assert_eq!(state.standup().blocker, vec!["shipped"]);
+
+        state.handle(Key::Char('d'));
+        state.app.reload().unwrap();
+        assert_eq!(state.entries().len(), 1);
+    }
+
+    #[test]
+    fn it_can_reorder_and_move_entries() {
+        let mut state = State::new(app("tui-move", "2016-03-04"));
+        press(&mut state, "tone\nttwo\n\tjK");
+        assert_eq!(state.standup().today, vec!["two", "one"]);
+        assert_eq!(state.selected_entry, 0);
+        press(&mut state, ">>");
+        assert_eq!(state.standup().today, vec!["one"]);
+        assert_eq!(state.standup().blocker, vec!["two"]);
+        assert_eq!(state.entries()[state.selected_entry].2, "two");
+    }
+
+    #[test]
+    fn it_can_search_the_days() {
+        let mut state = State::new(app("tui-search", "2016-03-04"));
+        press(&mut state, "tdeploy\n");
+        state.app.set_date(NaiveDate::from_ymd_opt(2016, 3, 3).unwrap());
+        state.app.record(Aspect::Today, "review".to_string()).unwrap();
+        press(&mut state, "/");
+        assert_eq!(state.dates().len(), 2);
+        press(&mut state, "REV\n");
+        assert_eq!(state.dates(), &[NaiveDate::from_ymd_opt(2016, 3, 3).unwrap()]);
+        assert_eq!(state.standup().today, vec!["review"]);
+        press(&mut state, "/\x1b");
+        assert_eq!(state.dates().len(), 2);
+    }
+}
@@ -0,0 +1,82 @@
+use std::thread;
+use std::time::Duration;
+use ureq;
+use ::CliError;
+
+/// Posts the JSON payload to an incoming webhook. When the webhook cannot be
+/// reached, is rate limiting or has a server error, it is tried again up to
+/// `attempts` times in all, waiting a little longer before each retry.
+pub fn post(url: &str, payload: &str, attempts: u32, pause: Duration) -> Result<(), CliError> {
+    let mut last = String::new();
+    for attempt in 1..(attempts + 1) {
+        if attempt > 1 {
+            thread::sleep(pause * (attempt - 1));
+        }
+        let response = ureq::post(url)
+            .set("Content-Type", "application/json")
+            .timeout(Duration::from_secs(10))
+            .send_string(payload);
+        match response {
+            Ok(_) => return Ok(()),
+            Err(ureq::Error::Status(code, _)) if code == 429 || code >= 500 => {
+                last = format!("the webhook answered {}", code);
+            },
+            Err(ureq::Error::Status(code, response)) => {
+                let body = response.into_string().unwrap_or(String::new());
+                return Err(CliError::Http(format!("the webhook refused the message ({}): {}", code, body.trim())));
+            },
+            Err(err) => last = err.to_string(),
+        }
+    }
+    Err(CliError::Http(format!("could not post to the webhook after {} attempts, {}", attempts, last)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+    use std::time::Duration;
+    use tiny_http::{Server, Response};
+
+    /// A webhook on an ephemeral port that answers with each status in turn,
+    /// sending back the bodies it receives.
+    fn stub(statuses: Vec<u16>) -> (String, Receiver<String>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/standup", server.server_addr().to_ip().unwrap());
+        let (sender, bodies) = channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let mut request = server.recv().unwrap();
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                sender.send(body).unwrap();
+                request.respond(Response::from_string("ok").with_status_code(status)).unwrap();
+            }
+        });
+        (url, bodies)
+    }
+
+    #[test]
+    fn it_will_retry_server_errors() {
+        let (url, bodies) = stub(vec![503, 500, 200]);
+        post(&url, "{\"text\":\"hi\"}", 3, Duration::from_millis(0)).unwrap();
+        let bodies: Vec<String> = bodies.iter().collect();
+        assert_eq!(bodies, vec!["{\"text\":\"hi\"}"; 3]);
+    }
+
+    #[test]
+    fn it_will_give_up_after_the_last_attempt() {
+        let (url, bodies) = stub(vec![500, 500]);
+        assert!(post(&url, "{}", 2, Duration::from_millis(0)).is_err());
+        assert_eq!(bodies.iter().count(), 2);
+    }
+
+    #[test]
+    fn it_will_not_retry_a_refused_message() {
+        let (url, bodies) = stub(vec![400]);
+        let err = post(&url, "{}", 3, Duration::from_millis(0)).unwrap_err();
+        assert!(err.to_string().contains("(400): ok"));
+        assert_eq!(bodies.iter().count(), 1);
+    }
+}
@@ -0,0 +1,111 @@
+use std::io::{BufRead, Write};
+use ::CliError;
+use standup::{Standup, Aspect, Layout};
+
+///
+/// ## Wizard
+///
+/// Walks through a standup one question at a time. The previous workday's
+/// today entries are offered first, to mark as done (they become yesterday
+/// entries) or carry (they stay on today). Then new today entries and
+/// blockers are asked for, one per line, until a blank line.
+///
+/// Nothing is written here; the finished standup is handed back so it can be
+/// saved in one go, or `None` when the input ends before the last question.
+///
+pub struct Wizard<'a, R: BufRead + 'a, W: Write + 'a> {
+    input: &'a mut R,
+    output: &'a mut W,
+}
+
+impl<'a, R: BufRead, W: Write> Wizard<'a, R, W> {
+    pub fn new(input: &'a mut R, output: &'a mut W) -> Wizard<'a, R, W> {
+        Wizard { input: input, output: output }
+    }
+
+    pub fn run(&mut self, standup: Standup, previous: Option<&Standup>, layout: &Layout)
+               -> Result<Option<Standup>, CliError> {
+        let mut standup = standup;
+        try!(self.say(&format!("Standup for {}\n", standup.date.format(&layout.date_format))));
+
+        if let Some(previous) = previous.filter(|previous| !previous.today.is_empty()) {
+            try!(self.say(&format!("On {} you were working on:\n", previous.date.format(&layout.date_format))));
+            for message in previous.today.iter() {
+                loop {
+                    let answer = match try!(self.ask(&format!("  {} - [d]one, [c]arry or [s]kip? ", message))) {
+                        Some(answer) => answer.to_lowercase(),
+                        None => return Ok(None),
+                    };
+                    if answer.starts_with("d") {
+                        standup = standup.add(Aspect::Yesterday, message);
+                    } else if answer.starts_with("c") {
+                        standup = standup.add(Aspect::Today, message);
+                    } else if !answer.starts_with("s") {
+                        continue;
+                    }
+                    break;
+                }
+            }
+        }
+
+        for &(aspect, question) in [(Aspect::Today, "What are you working on today?"),
+                                    (Aspect::Blocker, "Is anything blocking you?")].iter() {
+            try!(self.say(&format!("{} One per line, blank to finish.\n", question)));
+            loop {
+                match try!(self.ask("> ")) {
+                    Some(ref line) if line.is_empty() => break,
+                    Some(line) => standup = standup.add(aspect, &line),
+                    None => return Ok(None),
+                }
+            }
+        }
+        Ok(Some(standup))
+    }
+
+    fn say(&mut self, text: &str) -> Result<(), CliError> {
+        self.output.write_all(text.as_bytes())
+            .and_then(|_| self.output.flush())
+            .map_err(CliError::Io)
+    }
+
+    /// Prompts and reads a trimmed line, or `None` at the end of the input.
+    fn ask(&mut self, prompt: &str) -> Result<Option<String>, CliError> {
+        try!(self.say(prompt));
+        let mut line = String::new();
+        match try!(self.input.read_line(&mut line).map_err(CliError::Io)) {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim().to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::*;
+    use standup::{Standup, Aspect, Layout};
+
+    fn run(input: &str, previous: Option<&Standup>) -> Option<Standup> {
+        let mut input = input.as_bytes();
+        let mut output = vec![];
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 7).unwrap());
+        Wizard::new(&mut input, &mut output).run(standup, previous, &Layout::default()).unwrap()
+    }
+
+    #[test]
+    fn it_will_build_the_standup_from_the_answers() {
+        let previous = Standup::from_date(NaiveDate::from_ymd_opt(2016, 3, 4).unwrap())
+            .add(Aspect::Today, "ship it")
+            .add(Aspect::Today, "write docs")
+            .add(Aspect::Today, "lunch");
+        let standup = run("done\nwhat?\nc\ns\nreview\n\nwaiting on ops\n\n", Some(&previous)).unwrap();
+        assert_eq!(standup.yesterday, vec!["ship it"]);
+        assert_eq!(standup.today, vec!["write docs", "review"]);
+        assert_eq!(standup.blocker, vec!["waiting on ops"]);
+    }
+
+    #[test]
+    fn it_will_give_up_when_the_input_ends() {
+        assert_eq!(run("review\n", None), None);
+    }
+}
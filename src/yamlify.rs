@@ -0,0 +1,97 @@
+use standup::{Standup, Aspect};
+use serde_yaml::{to_string, Mapping, Value};
+use serde_yaml::Result;
+
+/// Mirrors the JSON model: a sequence of mappings with a date and a list of
+/// messages per aspect.
+pub fn serialize(standups: &[&Standup]) -> Result<String> {
+    let sequence = standups.iter().map(|standup| {
+        let mut mapping = Mapping::new();
+        mapping.insert(key("date"), Value::String(standup.date.format("%F").to_string()));
+        for aspect in Aspect::all() {
+            let messages = standup.messages(aspect).iter().map(|m| Value::String(m.clone()));
+            mapping.insert(key(aspect.name()), Value::Sequence(messages.collect()));
+        }
+        if let Some(ref author) = standup.author {
+            mapping.insert(key("author"), Value::String(author.clone()));
+        }
+        Value::Mapping(mapping)
+    });
+    to_string(&Value::Sequence(sequence.collect()))
+}
+
+fn key(name: &str) -> Value {
+    Value::String(name.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::*;
+    use serde_yaml::from_str;
+    use standup::{Standup, Aspect};
+
+    /// Reads standups back, to check what was written.
+    fn deserialize(yaml: String) -> Result<Vec<Standup>> {
+        from_str::<Value>(&yaml).map(|parsed| {
+            parsed.as_sequence()
+                .map_or(vec![], |items| {
+                    items.iter()
+                        .filter_map(|item| item.as_mapping())
+                        .map(build_standup)
+                        .collect()
+                })
+        })
+    }
+
+    fn build_standup(mapping: &Mapping) -> Standup {
+        let standup = Aspect::all().into_iter().fold(Standup::new(), |s, aspect| {
+            mapping.get(&key(aspect.name()))
+                .and_then(|value| value.as_sequence())
+                .map_or(s.clone(), |messages| {
+                    messages
+                        .iter()
+                        .filter_map(|value| value.as_str())
+                        .fold(s, |s, msg| s.add(aspect, msg))
+                })
+        });
+        let author = mapping.get(&key("author")).and_then(|value| value.as_str()).map(|a| a.to_string());
+        let standup = standup.set_author(author);
+        mapping.get(&key("date"))
+            .and_then(|value| value.as_str())
+            .and_then(|date_string| NaiveDate::parse_from_str(date_string, "%F").ok())
+            .map_or(standup.clone(), |date| {
+                standup.set_date(date)
+            })
+    }
+
+    #[test]
+    fn it_will_include_the_date_and_aspects() {
+        let standup = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "today");
+        let yaml = serialize(&[&standup]).unwrap();
+        assert!(yaml.contains("date: 2015-03-23"));
+        assert!(yaml.contains("today:\n    - today"));
+        assert!(yaml.contains("yesterday: []"));
+        assert!(yaml.contains("blocker: []"));
+    }
+
+    #[test]
+    fn it_will_round_trip() {
+        let first = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 23).unwrap())
+            .add(Aspect::Today, "today: with a colon")
+            .add(Aspect::Yesterday, "- looks like a list")
+            .add(Aspect::Blocker, "'quoted'");
+        let second = Standup::from_date(NaiveDate::from_ymd_opt(2015, 3, 24).unwrap());
+        let standups = deserialize(serialize(&[&first, &second]).unwrap()).unwrap();
+        assert_eq!(standups, vec![first, second]);
+    }
+
+    #[test]
+    fn it_will_ignore_unknown_keys() {
+        let yaml = "- date: 2015-03-23\n  mood: great\n  today: [one]\n".to_string();
+        let standups = deserialize(yaml).unwrap();
+        assert_eq!(standups[0].today, vec!["one"]);
+        assert_eq!(standups[0].date, NaiveDate::from_ymd_opt(2015, 3, 23).unwrap());
+    }
+}